@@ -0,0 +1,201 @@
+//! Blocking facade for the asynchronous [crate::Client].
+//!
+//! Every method blocks the current thread until the request to the node completes. This is
+//! intended for command line tools and tests that do not run an executor.
+//!
+//! ```no_run
+//! let client = oscoin_client::blocking::Client::new_from_file().unwrap();
+//! let pong = client.ping().unwrap();
+//! ```
+use futures::executor::block_on;
+use futures::stream::TryStreamExt;
+
+use crate::{
+    Account, AccountId, Address, BlockNumber, Checkpoint, Contribution, DependencyUpdate, Error,
+    Hash, LedgerEvent, Oscoin, Project, ProjectFilter, ProjectId, ProjectPage,
+    ReadContractAddressError, StatePage, StateSnapshot,
+};
+
+/// Provides blocking access to the Oscoin Ledger contract through a node. See [crate::Client]
+/// for the documentation of the methods.
+///
+/// Use [crate::ClientBuilder] to configure the client and convert it with [From].
+#[derive(Clone)]
+pub struct Client {
+    client: crate::Client,
+}
+
+impl From<crate::Client> for Client {
+    fn from(client: crate::Client) -> Client {
+        Client { client }
+    }
+}
+
+impl Client {
+    /// See [crate::Client::new].
+    pub fn new(ledger_address: Address) -> Client {
+        crate::Client::new(ledger_address).into()
+    }
+
+    /// See [crate::Client::new_from_file].
+    pub fn new_from_file() -> Result<Client, ReadContractAddressError> {
+        crate::Client::new_from_file().map(Client::from)
+    }
+
+    /// Returns the asynchronous client this client wraps.
+    pub fn async_client(&self) -> &crate::Client {
+        &self.client
+    }
+
+    pub fn new_account(&self) -> Result<Address, web3::error::Error> {
+        block_on(self.client.new_account())
+    }
+
+    pub fn ping(&self) -> Result<String, Error> {
+        block_on(self.client.ping())
+    }
+
+    pub fn counter_value(&self) -> Result<u32, Error> {
+        block_on(self.client.counter_value())
+    }
+
+    pub fn counter_inc(&self, sender: Address) -> Result<(), Error> {
+        block_on(self.client.counter_inc(sender))
+    }
+
+    pub fn register_project(
+        &self,
+        sender: Address,
+        name: String,
+        description: String,
+        img_url: String,
+    ) -> Result<ProjectId, Error> {
+        block_on(
+            self.client
+                .register_project(sender, name, description, img_url),
+        )
+    }
+
+    pub fn get_project(&self, project_id: ProjectId) -> Result<Option<Project>, Error> {
+        block_on(self.client.get_project(project_id))
+    }
+
+    pub fn list_projects_page(
+        &self,
+        offset: u64,
+        limit: u64,
+        filter: ProjectFilter,
+    ) -> Result<ProjectPage, Error> {
+        block_on(self.client.list_projects_page(offset, limit, filter))
+    }
+
+    /// Returns all projects that match `filter`. See [crate::Client::list_projects].
+    pub fn list_projects(&self, filter: ProjectFilter) -> Result<Vec<Project>, Error> {
+        block_on(self.client.list_projects(filter).try_collect())
+    }
+
+    pub fn unregister_project(&self, sender: Address, project_id: ProjectId) -> Result<(), Error> {
+        block_on(self.client.unregister_project(sender, project_id))
+    }
+
+    pub fn add_key(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        account: AccountId,
+    ) -> Result<(), Error> {
+        block_on(self.client.add_key(sender, project_id, account))
+    }
+
+    pub fn remove_key(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        account: AccountId,
+    ) -> Result<(), Error> {
+        block_on(self.client.remove_key(sender, project_id, account))
+    }
+
+    pub fn checkpoint(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        project_hash: Hash,
+        contributions: Vec<Contribution>,
+        dependency_updates: Vec<DependencyUpdate>,
+    ) -> Result<(), Error> {
+        block_on(self.client.checkpoint(
+            sender,
+            project_id,
+            project_hash,
+            contributions,
+            dependency_updates,
+        ))
+    }
+
+    pub fn get_checkpoint(
+        &self,
+        project_id: ProjectId,
+        index: u64,
+    ) -> Result<Option<Checkpoint>, Error> {
+        block_on(self.client.get_checkpoint(project_id, index))
+    }
+
+    pub fn get_dependencies(&self, project_id: ProjectId) -> Result<Vec<ProjectId>, Error> {
+        block_on(self.client.get_dependencies(project_id))
+    }
+
+    pub fn get_dependents(&self, project_id: ProjectId) -> Result<Vec<ProjectId>, Error> {
+        block_on(self.client.get_dependents(project_id))
+    }
+
+    pub fn transfer(&self, sender: Address, to: AccountId, amount: Oscoin) -> Result<(), Error> {
+        block_on(self.client.transfer(sender, to, amount))
+    }
+
+    pub fn get_account(&self, address: AccountId) -> Result<Account, Error> {
+        block_on(self.client.get_account(address))
+    }
+
+    pub fn pause(&self, sender: Address) -> Result<(), Error> {
+        block_on(self.client.pause(sender))
+    }
+
+    pub fn unpause(&self, sender: Address) -> Result<(), Error> {
+        block_on(self.client.unpause(sender))
+    }
+
+    pub fn transfer_ownership(&self, sender: Address, new_owner: AccountId) -> Result<(), Error> {
+        block_on(self.client.transfer_ownership(sender, new_owner))
+    }
+
+    pub fn is_paused(&self) -> Result<bool, Error> {
+        block_on(self.client.is_paused())
+    }
+
+    pub fn get_owners(&self) -> Result<Vec<AccountId>, Error> {
+        block_on(self.client.get_owners())
+    }
+
+    pub fn export_state_page(&self, cursor: u64) -> Result<StatePage, Error> {
+        block_on(self.client.export_state_page(cursor))
+    }
+
+    pub fn export_state(&self) -> Result<StateSnapshot, Error> {
+        block_on(self.client.export_state())
+    }
+
+    pub fn upgrade(&self, sender: Address, implementation: AccountId) -> Result<(), Error> {
+        block_on(self.client.upgrade(sender, implementation))
+    }
+
+    /// Returns the events emitted between `from_block` and `to_block`. See
+    /// [crate::Client::events].
+    pub fn events(
+        &self,
+        from_block: BlockNumber,
+        to_block: BlockNumber,
+    ) -> Result<Vec<LedgerEvent>, Error> {
+        block_on(self.client.events(from_block, to_block).try_collect())
+    }
+}
@@ -1,34 +1,55 @@
-use std::convert::From;
 ///! Client library for interacting with the oscoin ledger on a Parity Ethereum node.
 ///
 /// # Getting Started
 ///
 /// ```no_run
-/// # use futures::Future;
 /// let client = oscoin_client::Client::new_from_file().unwrap();
-/// client.ping().wait().unwrap();
+/// futures::executor::block_on(client.ping()).unwrap();
 /// ```
+///
+/// The [Client] is asynchronous. Command line tools can use the [blocking::Client] instead.
+use std::cmp;
+use std::collections::HashMap;
+use std::convert::From;
 use std::error;
 use std::fmt;
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use ethereum_types::U64;
-use futures::future::Future;
-use web3::transports::http::Http;
+use futures::compat::Future01CompatExt;
+use futures::future::{BoxFuture, FutureExt, TryFutureExt};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
 use web3::transports::EventLoopHandle;
-use web3::types::TransactionReceipt;
-pub use web3::types::{Address, H256, U256};
+pub use web3::types::{Address, BlockNumber, H256, U256};
+use web3::Transport;
 use web3::Web3;
 
-pub use oscoin_ledger::interface::{AccountId, ProjectId};
+pub use oscoin_ledger::error::{CheckpointError, KeysetError, LedgerError, TransferError};
+pub use oscoin_ledger::interface::{
+    Account, AccountId, Checkpoint, Contribution, DependencyUpdate, Hash, LedgerEvent, Oscoin,
+    Project, ProjectFilter, ProjectId, ProjectPage, StatePage, StateSnapshot, MAX_PAGE_SIZE,
+};
 use oscoin_ledger::{
-    compute_project_id, Call as LedgerCall, Project, Query as LedgerQuery, Update as LedgerUpdate,
+    Call as LedgerCall, Query as LedgerQuery, Update as LedgerUpdate, LEDGER_EVENT_TOPIC,
+    UPDATE_RESULT_TOPIC,
 };
 
+pub mod blocking;
+mod signer;
+mod transport;
+
+pub use signer::{Keystore, Signer, SignerError};
+pub use transport::{Endpoint, InvalidEndpointUrl, NodeTransport};
+
 /// URL pointing to a parity ethereum node running on localhost.
 ///
-/// This is the URL used by the client. It is currently not possible to change it.
-const LOCALHOST_NODE_URL: &str = "http://localhost:8545";
+/// This is the URL used by [Client::new] and the default for [ClientBuilder].
+pub const LOCALHOST_NODE_URL: &str = "http://localhost:8545";
+
+/// Default interval in which the client polls the node for transaction receipts.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 /// File Path to load and store the ledger contract address to. Is `./.oscoin_ledger_address`.
 pub const CONTRACT_ADDRESS_FILE: &str = "./.oscoin_ledger_address";
@@ -82,6 +103,12 @@ pub enum Error {
     /// Transaction failure is signaled by the `status` field in
     /// `TransactionReceipt.`
     TransactionFailure(H256),
+    /// The ledger rejected the query or transaction.
+    Ledger(LedgerError),
+    /// Signing the transaction on the client failed or there is no signer for the sender.
+    Signer(SignerError),
+    /// The URL passed to [ClientBuilder::node_url] is not supported.
+    InvalidEndpoint(InvalidEndpointUrl),
     Web3(web3::error::Error),
 }
 
@@ -93,6 +120,9 @@ impl fmt::Display for Error {
                 "Transaction execution failure. Transaction ID is: {}",
                 hash
             ),
+            Self::Ledger(ledger_error) => write!(f, "Ledger error: {:?}", ledger_error),
+            Self::Signer(signer_error) => write!(f, "Signer error: {}", signer_error),
+            Self::InvalidEndpoint(endpoint_error) => fmt::Display::fmt(&endpoint_error, f),
             Self::Web3(web3_error) => fmt::Display::fmt(&web3_error, f),
         }
     }
@@ -102,37 +132,224 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Self::TransactionFailure(_) => None,
+            Self::Ledger(_) => None,
+            Self::Signer(signer_error) => Some(signer_error),
+            Self::InvalidEndpoint(endpoint_error) => Some(endpoint_error),
             Self::Web3(web3_error) => Some(web3_error),
         }
     }
 }
 
+impl From<LedgerError> for Error {
+    fn from(ledger_error: LedgerError) -> Error {
+        Error::Ledger(ledger_error)
+    }
+}
+
+impl From<SignerError> for Error {
+    fn from(signer_error: SignerError) -> Error {
+        Error::Signer(signer_error)
+    }
+}
+
+impl From<InvalidEndpointUrl> for Error {
+    fn from(endpoint_error: InvalidEndpointUrl) -> Error {
+        Error::InvalidEndpoint(endpoint_error)
+    }
+}
+
+impl From<web3::error::Error> for Error {
+    fn from(web3_error: web3::error::Error) -> Error {
+        Error::Web3(web3_error)
+    }
+}
+
 pub fn read_contract_address() -> Result<Address, ReadContractAddressError> {
     let contract_address_hex = std::fs::read_to_string(CONTRACT_ADDRESS_FILE)?;
     Address::from_str(&contract_address_hex).map_err(ReadContractAddressError::HexError)
 }
 
+/// Builds a [Client] that talks to a node other than the one running on localhost or that uses
+/// non-default settings.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use oscoin_client::{ClientBuilder, Endpoint};
+/// # let ledger_address = oscoin_client::read_contract_address().unwrap();
+/// let client = ClientBuilder::new(ledger_address)
+///     .endpoint(Endpoint::WebSocket("ws://node.example.com:8546".to_string()))
+///     .request_timeout(Duration::from_secs(30))
+///     .confirmations(2)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    ledger_address: Address,
+    endpoint: Result<Endpoint, InvalidEndpointUrl>,
+    request_timeout: Option<Duration>,
+    poll_interval: Duration,
+    confirmations: usize,
+    signer: Option<Arc<dyn Signer>>,
+    node_signing: Option<bool>,
+    node_signer_password: String,
+    chain_id: Option<u64>,
+}
+
+impl ClientBuilder {
+    /// Creates a builder for a client calling the ledger at the given contract address.
+    ///
+    /// By default the client talks to [LOCALHOST_NODE_URL] over HTTP without a request timeout,
+    /// polls for receipts every [DEFAULT_POLL_INTERVAL] and does not wait for confirmations.
+    pub fn new(ledger_address: Address) -> ClientBuilder {
+        ClientBuilder {
+            ledger_address,
+            endpoint: Ok(Endpoint::Http(LOCALHOST_NODE_URL.to_string())),
+            request_timeout: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            confirmations: 0,
+            signer: None,
+            node_signing: None,
+            node_signer_password: String::new(),
+            chain_id: None,
+        }
+    }
+
+    /// Sets the node endpoint and transport the client connects to.
+    pub fn endpoint(mut self, endpoint: Endpoint) -> Self {
+        self.endpoint = Ok(endpoint);
+        self
+    }
+
+    /// Sets the node endpoint from a URL. See [Endpoint::from_url].
+    ///
+    /// If the URL is not supported [ClientBuilder::build] fails with [Error::InvalidEndpoint].
+    pub fn node_url(mut self, url: &str) -> Self {
+        self.endpoint = Endpoint::from_url(url);
+        self
+    }
+
+    /// Fail JSON-RPC requests to the node that take longer than `timeout`.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the interval in which the client polls the node for transaction receipts.
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the number of blocks that need to be mined on top of the block that includes a
+    /// transaction before the transaction result is returned.
+    pub fn confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    /// Signs transactions on the client with `signer`, for example a [Keystore].
+    ///
+    /// Transactions from accounts that `signer` does not hold a key for are rejected with
+    /// [SignerError::UnknownAccount] unless [ClientBuilder::node_signing] is enabled.
+    pub fn signer(mut self, signer: impl Signer + 'static) -> Self {
+        self.signer = Some(Arc::new(signer));
+        self
+    }
+
+    /// Enables or disables signing transactions with the accounts managed by the node through
+    /// `personal_signTransaction`. If a [ClientBuilder::signer] is set the node is only used
+    /// for senders the signer has no key for.
+    ///
+    /// Node signing is enabled by default if no signer is set and disabled otherwise.
+    pub fn node_signing(mut self, enabled: bool) -> Self {
+        self.node_signing = Some(enabled);
+        self
+    }
+
+    /// Sets the password the node uses to unlock the sender account when it signs a transaction
+    /// with `personal_signTransaction`. Defaults to the empty password.
+    pub fn node_signer_password(mut self, password: &str) -> Self {
+        self.node_signer_password = password.to_string();
+        self
+    }
+
+    /// Sets the chain ID used to sign transactions on the client. If it is not set the chain ID
+    /// is requested from the node with `eth_chainId`.
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Connects to the node and creates the client.
+    pub fn build(self) -> Result<Client, Error> {
+        // A single timer thread serves request timeouts and receipt polling. The default
+        // `max_timeout` of the timer is about seven minutes and would make longer sleeps fail.
+        let timer = tokio_timer::wheel()
+            .max_timeout(cmp::max(
+                self.poll_interval,
+                self.request_timeout.unwrap_or_default(),
+            ))
+            .build();
+        let (event_loop_handle, transport) =
+            NodeTransport::new(&self.endpoint?, self.request_timeout, timer.clone())?;
+        let node_signing = self.node_signing.unwrap_or_else(|| self.signer.is_none());
+        let inner = ClientInner {
+            _event_loop_handle: event_loop_handle,
+            web3: Web3::new(transport),
+            timer,
+            ledger_address: self.ledger_address,
+            poll_interval: self.poll_interval,
+            confirmations: self.confirmations,
+            signer: self.signer,
+            node_signing,
+            node_signer_password: self.node_signer_password,
+            chain_id: self.chain_id,
+            next_nonces: Mutex::new(HashMap::new()),
+        };
+        Ok(Client {
+            inner: Arc::new(inner),
+        })
+    }
+}
+
 /// Provides access to the Oscoin Ledger contract through a node.
 ///
-/// If a client is dropped the IO event loop is dropped, too and the client requests will error.
+/// All methods return futures that are `Send + 'static` and can be spawned on any futures 0.3
+/// executor. The client is cheap to clone. Clones share the connection to the node. Requests
+/// keep the connection alive so that dropping the client does not cancel in-flight requests.
+///
+/// Use [blocking::Client] if you don't run an executor.
+#[derive(Clone)]
 pub struct Client {
+    inner: Arc<ClientInner>,
+}
+
+/// State shared by all clones of a [Client] and the requests made by them.
+struct ClientInner {
     _event_loop_handle: EventLoopHandle,
-    web3: Web3<Http>,
+    web3: Web3<NodeTransport>,
+    timer: tokio_timer::Timer,
     ledger_address: Address,
+    poll_interval: Duration,
+    confirmations: usize,
+    signer: Option<Arc<dyn Signer>>,
+    node_signing: bool,
+    node_signer_password: String,
+    chain_id: Option<u64>,
+    /// Nonce of the next transaction for every sender that submitted a transaction. Holding the
+    /// lock of a sender serializes signing and sending the transactions of that sender.
+    next_nonces: Mutex<HashMap<Address, Arc<futures::lock::Mutex<Option<U256>>>>>,
 }
 
 // Public methods
 impl Client {
-    /// Creates a new client calling the ledger at the given contract address.
+    /// Creates a new client calling the ledger at the given contract address through the node
+    /// at [LOCALHOST_NODE_URL]. Use [ClientBuilder] to configure the node and transport.
     pub fn new(ledger_address: Address) -> Client {
-        let (event_loop_handle, http) = web3::transports::Http::new(LOCALHOST_NODE_URL)
-            .expect("Node URL is hardcoded and valid");
-        let web3 = web3::Web3::new(http);
-        Client {
-            _event_loop_handle: event_loop_handle,
-            web3,
-            ledger_address,
-        }
+        ClientBuilder::new(ledger_address)
+            .build()
+            .expect("Node URL is hardcoded and valid")
     }
 
     /// Creates a new client using the contract address stored in [CONTRACT_ADDRESS_FILE]. See
@@ -142,8 +359,10 @@ impl Client {
         Ok(Self::new(contract_address))
     }
 
+    /// Creates a new account on the node. The key of the account is managed by the node. To sign
+    /// with keys managed by the client use a [Keystore].
     pub fn new_account(&self) -> CallFuture<Address> {
-        self.web3.personal().new_account("")
+        self.inner.web3.personal().new_account("").compat().boxed()
     }
 
     pub fn ping(&self) -> QueryResult<String> {
@@ -158,13 +377,16 @@ impl Client {
         self.submit(sender, LedgerUpdate::CounterInc)
     }
 
-    pub fn register_project<'a>(
-        &'a self,
+    /// Registers a new project with `sender` as its only member and returns the ID assigned by
+    /// the ledger. The ID is read from the update result log of the transaction, so it is
+    /// correct even if other registrations are included in the same block.
+    pub fn register_project(
+        &self,
         sender: Address,
         name: String,
         description: String,
         img_url: String,
-    ) -> impl Future<Item = ProjectId, Error = Error> + 'a {
+    ) -> SubmitResult<ProjectId> {
         self.submit(
             sender,
             LedgerUpdate::RegisterProject {
@@ -173,12 +395,6 @@ impl Client {
                 img_url,
             },
         )
-        .map(move |receipt| {
-            let block = receipt
-                .block_number
-                .expect("Receipt must have block number");
-            compute_project_id(sender.as_fixed_bytes().into(), block.as_u64())
-        })
     }
 
     pub fn get_project(
@@ -188,51 +404,259 @@ impl Client {
         self.query(LedgerQuery::GetProject { project_id })
     }
 
-    pub fn list_projects(&self) -> QueryResult<Vec<Project>> {
-        self.query(LedgerQuery::ListProjects)
+    /// Returns a page of the projects that match `filter`. See
+    /// [oscoin_ledger::Ledger::list_projects] for how `offset` and `limit` are interpreted.
+    pub fn list_projects_page(
+        &self,
+        offset: u64,
+        limit: u64,
+        filter: ProjectFilter,
+    ) -> QueryResult<ProjectPage> {
+        self.query(LedgerQuery::ListProjects {
+            offset,
+            limit,
+            filter,
+        })
+    }
+
+    /// Returns all projects that match `filter` by walking through all pages returned by
+    /// [Client::list_projects_page]. Pages are only requested from the ledger when the stream is
+    /// polled.
+    pub fn list_projects(&self, filter: ProjectFilter) -> ProjectStream {
+        let client = self.clone();
+        stream::unfold(Some(0), move |offset| {
+            let client = client.clone();
+            let filter = filter.clone();
+            async move {
+                let offset = offset?;
+                let page = client
+                    .list_projects_page(offset, MAX_PAGE_SIZE, filter)
+                    .await;
+                Some(match page {
+                    Ok(page) => (Ok(page.projects), page.next_offset),
+                    Err(err) => (Err(err), None),
+                })
+            }
+        })
+        .map_ok(|projects| stream::iter(projects.into_iter().map(Ok::<_, Error>)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Removes the project from the ledger. `sender` must be a member of the project.
+    pub fn unregister_project(&self, sender: Address, project_id: ProjectId) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::UnregisterProject { project_id })
+    }
+
+    /// Adds `account` to the members of the project. `sender` must be a member of the project.
+    pub fn add_key(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        account: AccountId,
+    ) -> SubmitResult {
+        self.submit(
+            sender,
+            LedgerUpdate::AddKey {
+                project_id,
+                account,
+            },
+        )
+    }
+
+    /// Removes `account` from the members of the project. `sender` must be a member of the
+    /// project.
+    pub fn remove_key(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        account: AccountId,
+    ) -> SubmitResult {
+        self.submit(
+            sender,
+            LedgerUpdate::RemoveKey {
+                project_id,
+                account,
+            },
+        )
+    }
+
+    /// Adds a checkpoint to the project. `sender` must be a member of the project.
+    ///
+    /// See [Checkpoint] for how `contributions` must be linked. The checkpoint is rejected if
+    /// any of the `dependency_updates` is invalid.
+    pub fn checkpoint(
+        &self,
+        sender: Address,
+        project_id: ProjectId,
+        project_hash: Hash,
+        contributions: Vec<Contribution>,
+        dependency_updates: Vec<DependencyUpdate>,
+    ) -> SubmitResult {
+        self.submit(
+            sender,
+            LedgerUpdate::Checkpoint {
+                project_id,
+                project_hash,
+                contributions,
+                dependency_updates,
+            },
+        )
+    }
+
+    /// Returns the checkpoint of the project with the given zero-based index.
+    pub fn get_checkpoint(
+        &self,
+        project_id: ProjectId,
+        index: u64,
+    ) -> QueryResult<Option<Checkpoint>> {
+        self.query(LedgerQuery::GetCheckpoint { project_id, index })
+    }
+
+    /// Returns the projects the given project depends on.
+    pub fn get_dependencies(&self, project_id: ProjectId) -> QueryResult<Vec<ProjectId>> {
+        self.query(LedgerQuery::GetDependencies { project_id })
+    }
+
+    /// Returns the projects that depend on the given project.
+    pub fn get_dependents(&self, project_id: ProjectId) -> QueryResult<Vec<ProjectId>> {
+        self.query(LedgerQuery::GetDependents { project_id })
+    }
+
+    /// Transfers `amount` Oscoin from the account of `sender` to `to`.
+    pub fn transfer(&self, sender: Address, to: AccountId, amount: Oscoin) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::Transfer { to, amount })
+    }
+
+    pub fn get_account(&self, address: AccountId) -> QueryResult<Account> {
+        self.query(LedgerQuery::GetAccount { address })
+    }
+
+    /// Pauses the ledger. While the ledger is paused all updates except [Client::unpause] fail
+    /// with [LedgerError::Paused]. `sender` must be an owner of the ledger.
+    pub fn pause(&self, sender: Address) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::Pause)
+    }
+
+    /// Resumes accepting updates after [Client::pause]. `sender` must be an owner of the ledger.
+    pub fn unpause(&self, sender: Address) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::Unpause)
+    }
+
+    /// Replaces `sender` in the set of ledger owners with `new_owner`.
+    pub fn transfer_ownership(&self, sender: Address, new_owner: AccountId) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::TransferOwnership { new_owner })
+    }
+
+    pub fn is_paused(&self) -> QueryResult<bool> {
+        self.query(LedgerQuery::IsPaused)
+    }
+
+    pub fn get_owners(&self) -> QueryResult<Vec<AccountId>> {
+        self.query(LedgerQuery::GetOwners)
+    }
+
+    /// Returns a page of the ledger state. See [oscoin_ledger::Ledger::export_state].
+    pub fn export_state_page(&self, cursor: u64) -> QueryResult<StatePage> {
+        self.query(LedgerQuery::ExportState { cursor })
+    }
+
+    /// Exports the ledger state by walking through all pages returned by
+    /// [Client::export_state_page].
+    ///
+    /// The ledger should be paused while the state is exported. Otherwise the snapshot may be
+    /// inconsistent.
+    pub fn export_state(&self) -> QueryResult<StateSnapshot> {
+        let client = self.clone();
+        async move {
+            let mut snapshot = StateSnapshot::new();
+            let mut cursor = Some(0);
+            while let Some(current_cursor) = cursor {
+                let page = client.export_state_page(current_cursor).await?;
+                cursor = page.next_cursor;
+                snapshot.add_page(page);
+            }
+            Ok(snapshot)
+        }
+            .boxed()
+    }
+
+    /// Makes the ledger proxy forward all calls to the ledger implementation contract at
+    /// `implementation`. `sender` must be an owner of the ledger.
+    pub fn upgrade(&self, sender: Address, implementation: AccountId) -> SubmitResult {
+        self.submit(sender, LedgerUpdate::Upgrade { implementation })
+    }
+
+    /// Returns the [LedgerEvent]s emitted by the ledger between `from_block` and `to_block`
+    /// (inclusive) in the order they were emitted.
+    pub fn events(&self, from_block: BlockNumber, to_block: BlockNumber) -> EventStream {
+        let filter = web3::types::FilterBuilder::default()
+            .address(vec![self.inner.ledger_address])
+            .from_block(from_block)
+            .to_block(to_block)
+            .topics(Some(vec![H256::from(LEDGER_EVENT_TOPIC)]), None, None, None)
+            .build();
+        self.inner
+            .web3
+            .eth()
+            .logs(filter)
+            .compat()
+            .map_err(Error::Web3)
+            .map_ok(|logs| stream::iter(logs.into_iter().map(|log| decode_event(&log.data.0))))
+            .try_flatten_stream()
+            .boxed()
     }
 }
 
 // Private methods
 impl Client {
     /// Queries the ledger contract by calling a method with the given parameters.
-    fn query<R: serde::de::DeserializeOwned + 'static>(
+    fn query<R: serde::de::DeserializeOwned + Send + 'static>(
         &self,
         query: LedgerQuery,
     ) -> QueryResult<R> {
+        let inner = self.inner.clone();
+        async move { inner.query(query).await }.boxed()
+    }
+
+    /// Submit a ledger transaction that calls the given method with the given parameters on the
+    /// ledger contract.
+    fn submit<T: serde::de::DeserializeOwned + Send + 'static>(
+        &self,
+        sender: Address,
+        update: LedgerUpdate,
+    ) -> SubmitResult<T> {
+        let inner = self.inner.clone();
+        async move { inner.submit(sender, update).await }.boxed()
+    }
+}
+
+impl ClientInner {
+    async fn query<R: serde::de::DeserializeOwned>(&self, query: LedgerQuery) -> Result<R, Error> {
         let data = LedgerCall::Query(query).serialize();
-        let future = self
-            .web3
-            .eth()
-            .call(
-                web3::types::CallRequest {
-                    from: None,
-                    to: self.ledger_address,
-                    gas: None,
-                    gas_price: None,
-                    value: None,
-                    data: Some(web3::types::Bytes(data)),
-                },
-                None,
-            )
-            .and_then(|web3::types::Bytes(vec)| {
-                serde_cbor::from_slice(&vec).map_err(|err| {
-                    web3::error::Error::InvalidResponse(format!(
-                        "Failed to decode CBOR response: {}",
-                        err
-                    ))
-                })
-            });
-        QueryResult {
-            future: Box::new(future),
-        }
+        let call_request = web3::types::CallRequest {
+            from: None,
+            to: self.ledger_address,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: Some(web3::types::Bytes(data)),
+        };
+        let web3::types::Bytes(response) =
+            self.web3.eth().call(call_request, None).compat().await?;
+        decode_result(&response)
     }
 
     /// Submit a ledger transaction that calls the given method with the given parameters on the
     /// ledger contract.
     ///
-    /// Note that an error is only visible as a zero status in the [TransactionReceipt].
-    fn submit(&self, sender: Address, update: LedgerUpdate) -> SubmitResult {
+    /// The result of the ledger method is decoded from the log entry the ledger emits with the
+    /// [UPDATE_RESULT_TOPIC].
+    async fn submit<T: serde::de::DeserializeOwned>(
+        &self,
+        sender: Address,
+        update: LedgerUpdate,
+    ) -> Result<T, Error> {
         let data = LedgerCall::Update(update).serialize();
         let transaction_request = web3::types::TransactionRequest {
             from: sender,
@@ -245,62 +669,195 @@ impl Client {
             condition: None,
         };
 
-        let poll_interval = core::time::Duration::from_secs(1);
-        let future = self
-            .web3
-            .personal()
-            .sign_transaction(transaction_request, "")
-            .and_then(move |signed_tx| {
-                web3::confirm::send_raw_transaction_with_confirmation(
-                    self.web3.transport().clone(),
-                    signed_tx.raw,
-                    poll_interval,
-                    0,
-                )
+        let transaction_hash = self.sign_and_send(sender, transaction_request).await?;
+        let tx_receipt = self.wait_for_receipt(transaction_hash).await?;
+        if tx_receipt.status == Some(U64([0])) {
+            return Err(Error::TransactionFailure(tx_receipt.transaction_hash));
+        }
+
+        let update_result_topic = H256::from(UPDATE_RESULT_TOPIC);
+        let log = tx_receipt
+            .logs
+            .iter()
+            .find(|log| {
+                log.address == self.ledger_address && log.topics == vec![update_result_topic]
             })
-            .map_err(Error::Web3)
-            .and_then(move |tx_receipt| match tx_receipt.status {
-                Some(U64([0])) => Err(Error::TransactionFailure(tx_receipt.transaction_hash)),
-                _ => Ok(tx_receipt),
-            });
+            .ok_or_else(|| {
+                web3::error::Error::InvalidResponse(format!(
+                    "Transaction {} has no update result log",
+                    tx_receipt.transaction_hash
+                ))
+            })?;
+        decode_result(&log.data.0)
+    }
 
-        SubmitResult {
-            future: Box::new(future),
+    /// Signs `transaction_request` for `sender`, sends it to the node and returns the
+    /// transaction hash.
+    ///
+    /// Transactions from the same sender are signed and sent one after another so that
+    /// concurrently submitted transactions get consecutive nonces.
+    async fn sign_and_send(
+        &self,
+        sender: Address,
+        transaction_request: web3::types::TransactionRequest,
+    ) -> Result<H256, Error> {
+        let next_nonce = self.next_nonce(sender);
+        let mut next_nonce = next_nonce.lock().await;
+        let raw_tx = self
+            .sign(sender, transaction_request, &mut next_nonce)
+            .await?;
+        let result = self.web3.eth().send_raw_transaction(raw_tx).compat().await;
+        if result.is_err() {
+            // The nonce may not have been used. Request it from the node for the next
+            // transaction.
+            *next_nonce = None;
         }
+        Ok(result?)
     }
-}
 
-/// Returned by queries to the ledger contract.
-///
-/// The [Future] interfaces allows one to retrieve the result of the query.
-pub struct QueryResult<'a, T> {
-    future: Box<dyn Future<Item = T, Error = web3::error::Error> + 'a>,
-}
+    /// Returns the lock guarding the nonce of the next transaction from `sender`.
+    fn next_nonce(&self, sender: Address) -> Arc<futures::lock::Mutex<Option<U256>>> {
+        self.next_nonces
+            .lock()
+            .expect("Nonce lock poisoned")
+            .entry(sender)
+            .or_insert_with(|| Arc::new(futures::lock::Mutex::new(None)))
+            .clone()
+    }
 
-impl<'a, T> Future for QueryResult<'a, T> {
-    type Item = T;
-    type Error = web3::error::Error;
+    /// Signs `transaction_request` for `sender` and returns the raw signed transaction.
+    ///
+    /// If the [Signer] holds the key for `sender` the transaction is signed on the client. The
+    /// gas price and gas are requested from the node. The nonce is the larger of `next_nonce`
+    /// and the transaction count of `sender` including pending transactions. `next_nonce` is
+    /// advanced to the following nonce. Otherwise the
+    /// transaction is signed by the node with the configured node signer password if node signing
+    /// is enabled.
+    async fn sign(
+        &self,
+        sender: Address,
+        mut transaction_request: web3::types::TransactionRequest,
+        next_nonce: &mut Option<U256>,
+    ) -> Result<web3::types::Bytes, Error> {
+        match &self.signer {
+            Some(signer) if signer.has_account(sender) => {
+                let eth = self.web3.eth();
+                let call_request = web3::types::CallRequest {
+                    from: Some(sender),
+                    to: self.ledger_address,
+                    gas: None,
+                    gas_price: None,
+                    value: None,
+                    data: transaction_request.data.clone(),
+                };
+                let pending_nonce = eth
+                    .transaction_count(sender, Some(BlockNumber::Pending))
+                    .compat()
+                    .await?;
+                let nonce = match *next_nonce {
+                    Some(nonce) if nonce > pending_nonce => nonce,
+                    _ => pending_nonce,
+                };
+                let gas_price = eth.gas_price().compat().await?;
+                let gas = eth.estimate_gas(call_request, None).compat().await?;
+                let chain_id = self.chain_id().await?;
+                transaction_request.nonce = Some(nonce);
+                transaction_request.gas_price = Some(gas_price);
+                transaction_request.gas = Some(gas);
+                let raw_tx = signer.sign_transaction(sender, &transaction_request, chain_id)?;
+                *next_nonce = Some(nonce + U256::one());
+                Ok(web3::types::Bytes(raw_tx))
+            }
+            _ if self.node_signing => {
+                let signed_tx = self
+                    .web3
+                    .personal()
+                    .sign_transaction(transaction_request, &self.node_signer_password)
+                    .compat()
+                    .await?;
+                Ok(signed_tx.raw)
+            }
+            _ => Err(Error::Signer(SignerError::UnknownAccount(sender))),
+        }
+    }
 
-    fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
-        self.future.poll()
+    /// Returns the configured chain ID or requests it from the node.
+    async fn chain_id(&self) -> Result<u64, Error> {
+        match self.chain_id {
+            Some(chain_id) => Ok(chain_id),
+            None => {
+                let chain_id: U256 = web3::helpers::CallFuture::new(
+                    self.web3.transport().execute("eth_chainId", vec![]),
+                )
+                .compat()
+                .await?;
+                Ok(chain_id.low_u64())
+            }
+        }
     }
+
+    /// Waits until the transaction is included in a block and the configured number of
+    /// confirmations is reached. The node is polled for the receipt every `poll_interval`.
+    async fn wait_for_receipt(
+        &self,
+        transaction_hash: H256,
+    ) -> Result<web3::types::TransactionReceipt, Error> {
+        let eth = self.web3.eth();
+        loop {
+            if let Some(tx_receipt) = eth.transaction_receipt(transaction_hash).compat().await? {
+                if self.confirmations == 0 {
+                    return Ok(tx_receipt);
+                }
+                if let Some(tx_block_number) = tx_receipt.block_number {
+                    let block_number = eth.block_number().compat().await?;
+                    if block_number >= tx_block_number + self.confirmations {
+                        return Ok(tx_receipt);
+                    }
+                }
+            }
+            self.timer
+                .sleep(self.poll_interval)
+                .compat()
+                .await
+                .map_err(|err| web3::error::Error::Transport(format!("Timer failed: {}", err)))?;
+        }
+    }
+}
+
+/// Decodes the CBOR encoded `Result<T, LedgerError>` returned by the ledger.
+fn decode_result<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T, Error> {
+    let result: Result<T, LedgerError> = serde_cbor::from_slice(data).map_err(|err| {
+        web3::error::Error::InvalidResponse(format!("Failed to decode CBOR response: {}", err))
+    })?;
+    Ok(result?)
+}
+
+/// Decodes a CBOR encoded [LedgerEvent] emitted by the ledger.
+fn decode_event(data: &[u8]) -> Result<LedgerEvent, Error> {
+    serde_cbor::from_slice(data).map_err(|err| {
+        Error::Web3(web3::error::Error::InvalidResponse(format!(
+            "Failed to decode ledger event: {}",
+            err
+        )))
+    })
 }
 
+/// Returned by queries to the ledger contract.
+///
+/// Resolves to the result of the query.
+pub type QueryResult<T> = BoxFuture<'static, Result<T, Error>>;
+
 /// Returned by the client when submiting transactions to the ledger.
 ///
-/// The [Future] interfaces allows one to retrieve the result of the query.
-pub struct SubmitResult<'a> {
-    future: Box<dyn Future<Item = TransactionReceipt, Error = Error> + 'a>,
-}
+/// Resolves to the value returned by the ledger method once the transaction is included in a
+/// block.
+pub type SubmitResult<T = ()> = BoxFuture<'static, Result<T, Error>>;
 
-impl<'a> Future for SubmitResult<'a> {
-    type Item = TransactionReceipt;
-    type Error = Error;
+/// Returned by [Client::list_projects].
+pub type ProjectStream = BoxStream<'static, Result<Project, Error>>;
 
-    fn poll(&mut self) -> futures::Poll<Self::Item, Self::Error> {
-        self.future.poll()
-    }
-}
+/// Returned by [Client::events].
+pub type EventStream = BoxStream<'static, Result<LedgerEvent, Error>>;
 
 /// [Future] for API call results with error [web3::error::Error].
-pub type CallFuture<T> = web3::helpers::CallFuture<T, <Http as web3::Transport>::Out>;
+pub type CallFuture<T> = BoxFuture<'static, Result<T, web3::error::Error>>;
@@ -0,0 +1,477 @@
+//! Signing of ledger transactions on the client.
+//!
+//! A [Signer] holds the private keys of accounts and signs transactions for them without
+//! involving the node. [Keystore] is a [Signer] that holds secp256k1 keys imported from
+//! Parity Ethereum or Geth JSON key files.
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::path::Path;
+
+use aes_ctr::stream_cipher::{NewStreamCipher, SyncStreamCipher};
+use aes_ctr::Aes128Ctr;
+use hmac::Hmac;
+use rlp::RlpStream;
+use rustc_hex::FromHex;
+use scrypt::ScryptParams;
+use secp256k1::{Message, PublicKey, SecretKey};
+use serde::{Deserialize, Deserializer};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+use web3::types::{Address, TransactionRequest, U256};
+
+/// Signs transactions for a set of accounts.
+pub trait Signer: fmt::Debug + Send + Sync {
+    /// Returns `true` if the signer holds the key for `address`.
+    fn has_account(&self, address: Address) -> bool;
+
+    /// Signs `transaction` with the key of `sender` for the chain `chain_id` as described in
+    /// [EIP-155](https://eips.ethereum.org/EIPS/eip-155). Returns the RLP encoded signed
+    /// transaction that can be sent with `eth_sendRawTransaction`.
+    ///
+    /// The nonce, gas and gas price of `transaction` must be set.
+    fn sign_transaction(
+        &self,
+        sender: Address,
+        transaction: &TransactionRequest,
+        chain_id: u64,
+    ) -> Result<Vec<u8>, SignerError>;
+}
+
+/// Error returned when importing a key or signing a transaction fails.
+#[derive(Debug)]
+pub enum SignerError {
+    /// The signer does not hold the key for the account.
+    UnknownAccount(Address),
+    /// The transaction field with the given name is not set.
+    MissingField(&'static str),
+    /// The key file could not be read or parsed.
+    InvalidKeyFile(String),
+    /// The key could not be decrypted with the given password.
+    InvalidPassword,
+    /// Creating the signature failed.
+    Signing(String),
+}
+
+impl fmt::Display for SignerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownAccount(address) => write!(f, "no key for account {:x}", address),
+            Self::MissingField(field) => write!(f, "transaction field {} is not set", field),
+            Self::InvalidKeyFile(reason) => write!(f, "invalid key file: {}", reason),
+            Self::InvalidPassword => write!(f, "failed to decrypt key with the given password"),
+            Self::Signing(reason) => write!(f, "failed to sign transaction: {}", reason),
+        }
+    }
+}
+
+impl error::Error for SignerError {}
+
+/// [Signer] that keeps secp256k1 keys in memory.
+///
+/// Keys are imported from password protected JSON key files as written by Parity Ethereum and
+/// Geth. The keys are decrypted when they are imported.
+///
+/// ```no_run
+/// # use oscoin_client::{ClientBuilder, Keystore};
+/// # let ledger_address = oscoin_client::read_contract_address().unwrap();
+/// let mut keystore = Keystore::new();
+/// keystore
+///     .import_key_file("./dev-node/dev-key.json", "")
+///     .unwrap();
+/// let client = ClientBuilder::new(ledger_address)
+///     .signer(keystore)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Default)]
+pub struct Keystore {
+    keys: HashMap<Address, SecretKey>,
+}
+
+impl Keystore {
+    /// Creates an empty keystore.
+    pub fn new() -> Keystore {
+        Keystore::default()
+    }
+
+    /// Imports the key from the JSON key file at `path` and decrypts it with `password`. Returns
+    /// the address of the imported account.
+    pub fn import_key_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        password: &str,
+    ) -> Result<Address, SignerError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            SignerError::InvalidKeyFile(format!("failed to read {}: {}", path.display(), e))
+        })?;
+        self.import_key_json(&json, password)
+    }
+
+    /// Imports the key from the content of a JSON key file and decrypts it with `password`.
+    /// Returns the address of the imported account.
+    pub fn import_key_json(&mut self, json: &str, password: &str) -> Result<Address, SignerError> {
+        let key_file: KeyFile =
+            serde_json::from_str(json).map_err(|e| SignerError::InvalidKeyFile(e.to_string()))?;
+        let secret = key_file.crypto.decrypt(password)?;
+        let address = secret_key_address(&secret);
+        self.keys.insert(address, secret);
+        Ok(address)
+    }
+
+    /// Returns the addresses of all accounts in the keystore.
+    pub fn accounts(&self) -> Vec<Address> {
+        self.keys.keys().cloned().collect()
+    }
+}
+
+impl fmt::Debug for Keystore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keystore")
+            .field("accounts", &self.accounts())
+            .finish()
+    }
+}
+
+impl Signer for Keystore {
+    fn has_account(&self, address: Address) -> bool {
+        self.keys.contains_key(&address)
+    }
+
+    fn sign_transaction(
+        &self,
+        sender: Address,
+        transaction: &TransactionRequest,
+        chain_id: u64,
+    ) -> Result<Vec<u8>, SignerError> {
+        let secret = self
+            .keys
+            .get(&sender)
+            .ok_or(SignerError::UnknownAccount(sender))?;
+        let unsigned =
+            encode_transaction(transaction, chain_id.into(), U256::zero(), U256::zero())?;
+        let hash = tiny_keccak::keccak256(&unsigned);
+        let (signature, recovery_id) = secp256k1::sign(&Message::parse(&hash), secret);
+        let signature = signature.serialize();
+        let v = u64::from(recovery_id.serialize()) + 35 + chain_id * 2;
+        encode_transaction(
+            transaction,
+            v.into(),
+            U256::from_big_endian(&signature[..32]),
+            U256::from_big_endian(&signature[32..]),
+        )
+    }
+}
+
+/// RLP encodes `transaction` with the signature fields `v`, `r` and `s`. For the EIP-155
+/// signing hash `v` is the chain ID and `r` and `s` are zero.
+fn encode_transaction(
+    transaction: &TransactionRequest,
+    v: U256,
+    r: U256,
+    s: U256,
+) -> Result<Vec<u8>, SignerError> {
+    let nonce = transaction
+        .nonce
+        .ok_or(SignerError::MissingField("nonce"))?;
+    let gas_price = transaction
+        .gas_price
+        .ok_or(SignerError::MissingField("gas_price"))?;
+    let gas = transaction.gas.ok_or(SignerError::MissingField("gas"))?;
+
+    let mut stream = RlpStream::new_list(9);
+    stream.append(&nonce);
+    stream.append(&gas_price);
+    stream.append(&gas);
+    match transaction.to {
+        Some(to) => stream.append(&to),
+        None => stream.append_empty_data(),
+    };
+    stream.append(&transaction.value.unwrap_or_default());
+    match &transaction.data {
+        Some(data) => stream.append(&data.0),
+        None => stream.append_empty_data(),
+    };
+    stream.append(&v);
+    stream.append(&r);
+    stream.append(&s);
+    Ok(stream.out())
+}
+
+/// Returns the address of the account with the given key, which are the last 20 bytes of the
+/// Keccak hash of the uncompressed public key.
+fn secret_key_address(secret: &SecretKey) -> Address {
+    let public = PublicKey::from_secret_key(secret).serialize();
+    let hash = tiny_keccak::keccak256(&public[1..]);
+    Address::from_slice(&hash[12..])
+}
+
+/// Version 3 JSON key file as described in the [Web3 Secret Storage
+/// Definition](https://github.com/ethereum/wiki/wiki/Web3-Secret-Storage-Definition).
+#[derive(Deserialize)]
+struct KeyFile {
+    #[serde(alias = "Crypto")]
+    crypto: KeyFileCrypto,
+}
+
+/// Encrypted key of a [KeyFile] together with the parameters to decrypt it.
+#[derive(Deserialize)]
+struct KeyFileCrypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    #[serde(deserialize_with = "deserialize_hex")]
+    ciphertext: Vec<u8>,
+    #[serde(flatten)]
+    kdf: Kdf,
+    #[serde(deserialize_with = "deserialize_hex")]
+    mac: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct CipherParams {
+    #[serde(deserialize_with = "deserialize_hex")]
+    iv: Vec<u8>,
+}
+
+/// Key derivation function that derives the decryption key from the password.
+#[derive(Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+enum Kdf {
+    Pbkdf2 {
+        c: u32,
+        dklen: usize,
+        prf: String,
+        #[serde(deserialize_with = "deserialize_hex")]
+        salt: Vec<u8>,
+    },
+    Scrypt {
+        n: u64,
+        r: u32,
+        p: u32,
+        dklen: usize,
+        #[serde(deserialize_with = "deserialize_hex")]
+        salt: Vec<u8>,
+    },
+}
+
+/// Length of the key derived from the password. The first half is the AES key and the second
+/// half is used for the MAC.
+const DERIVED_KEY_LEN: usize = 32;
+
+/// Largest number of PBKDF2 iterations accepted in a key file. Parity Ethereum uses 10240.
+const MAX_PBKDF2_ROUNDS: u32 = 1 << 20;
+
+/// Largest scrypt cost parameter `n` accepted in a key file as a power of two. With
+/// [MAX_SCRYPT_R] this limits the memory scrypt uses to 256 MiB, which is what Geth uses.
+const MAX_SCRYPT_LOG_N: u32 = 18;
+
+/// Largest scrypt block size `r` accepted in a key file.
+const MAX_SCRYPT_R: u32 = 8;
+
+/// Largest scrypt parallelization parameter `p` accepted in a key file.
+const MAX_SCRYPT_P: u32 = 16;
+
+impl KeyFileCrypto {
+    /// Derives the key from `password`, checks the MAC and decrypts the secret key.
+    fn decrypt(&self, password: &str) -> Result<SecretKey, SignerError> {
+        if self.cipher != "aes-128-ctr" {
+            return Err(SignerError::InvalidKeyFile(format!(
+                "unsupported cipher {}",
+                self.cipher
+            )));
+        }
+        let derived_key = self.kdf.derive_key(password.as_bytes())?;
+
+        let mut mac_data = derived_key[16..].to_vec();
+        mac_data.extend_from_slice(&self.ciphertext);
+        let mac = tiny_keccak::keccak256(&mac_data);
+        if !bool::from(mac[..].ct_eq(&self.mac)) {
+            return Err(SignerError::InvalidPassword);
+        }
+
+        let mut secret = self.ciphertext.clone();
+        Aes128Ctr::new_var(&derived_key[..16], &self.cipherparams.iv)
+            .map_err(|_| SignerError::InvalidKeyFile("invalid cipher IV".to_string()))?
+            .apply_keystream(&mut secret);
+        SecretKey::parse_slice(&secret)
+            .map_err(|_| SignerError::InvalidKeyFile("invalid secret key".to_string()))
+    }
+}
+
+impl Kdf {
+    /// Derives the decryption key from `password`. Fails if the parameters of the key file
+    /// exceed the limits of this module so that a key file cannot exhaust memory or time.
+    fn derive_key(&self, password: &[u8]) -> Result<[u8; DERIVED_KEY_LEN], SignerError> {
+        let mut key = [0; DERIVED_KEY_LEN];
+        match self {
+            Kdf::Pbkdf2 {
+                c,
+                dklen,
+                prf,
+                salt,
+            } => {
+                check_derived_key_len(*dklen)?;
+                if prf != "hmac-sha256" {
+                    return Err(SignerError::InvalidKeyFile(format!(
+                        "unsupported pbkdf2 PRF {}",
+                        prf
+                    )));
+                }
+                if *c == 0 || *c > MAX_PBKDF2_ROUNDS {
+                    return Err(SignerError::InvalidKeyFile(format!(
+                        "pbkdf2 iteration count {} is not between 1 and {}",
+                        c, MAX_PBKDF2_ROUNDS
+                    )));
+                }
+                pbkdf2::pbkdf2::<Hmac<Sha256>>(password, salt, *c, &mut key);
+            }
+            Kdf::Scrypt {
+                n,
+                r,
+                p,
+                dklen,
+                salt,
+            } => {
+                check_derived_key_len(*dklen)?;
+                let log_n = n.trailing_zeros();
+                if !n.is_power_of_two()
+                    || log_n == 0
+                    || log_n > MAX_SCRYPT_LOG_N
+                    || *r > MAX_SCRYPT_R
+                    || *p > MAX_SCRYPT_P
+                {
+                    return Err(SignerError::InvalidKeyFile(format!(
+                        "unsupported scrypt parameters n = {}, r = {}, p = {}",
+                        n, r, p
+                    )));
+                }
+                let params = ScryptParams::new(log_n as u8, *r, *p).map_err(|_| {
+                    SignerError::InvalidKeyFile(format!(
+                        "invalid scrypt parameters n = {}, r = {}, p = {}",
+                        n, r, p
+                    ))
+                })?;
+                scrypt::scrypt(password, salt, &params, &mut key)
+                    .expect("Derived key length is valid");
+            }
+        }
+        Ok(key)
+    }
+}
+
+fn check_derived_key_len(dklen: usize) -> Result<(), SignerError> {
+    if dklen == DERIVED_KEY_LEN {
+        Ok(())
+    } else {
+        Err(SignerError::InvalidKeyFile(format!(
+            "unsupported derived key length {}",
+            dklen
+        )))
+    }
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    hex.trim_start_matches("0x")
+        .from_hex()
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rustc_hex::ToHex;
+
+    #[test]
+    fn import_dev_key() {
+        let json = include_str!("../../dev-node/dev-key.json");
+        let mut keystore = Keystore::new();
+        let address = keystore.import_key_json(json, "").unwrap();
+        assert_eq!(
+            address,
+            "bcd6e47db1ac1f7f021988e20854d27778de6e4d".parse().unwrap()
+        );
+        assert!(keystore.has_account(address));
+
+        match Keystore::new().import_key_json(json, "wrong password") {
+            Err(SignerError::InvalidPassword) => (),
+            result => panic!("Unexpected result {:?}", result),
+        }
+    }
+
+    #[test]
+    fn sign_transaction_eip155() {
+        // Example transaction from https://eips.ethereum.org/EIPS/eip-155
+        let secret = SecretKey::parse(&[0x46; 32]).unwrap();
+        let sender = secret_key_address(&secret);
+        let mut keystore = Keystore::new();
+        keystore.keys.insert(sender, secret);
+        let transaction = TransactionRequest {
+            from: sender,
+            to: Some("3535353535353535353535353535353535353535".parse().unwrap()),
+            gas: Some(21000.into()),
+            gas_price: Some(20_000_000_000u64.into()),
+            value: Some(1_000_000_000_000_000_000u64.into()),
+            data: None,
+            nonce: Some(9.into()),
+            condition: None,
+        };
+        let signed = keystore.sign_transaction(sender, &transaction, 1).unwrap();
+        assert_eq!(
+            signed.to_hex::<String>(),
+            "f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400\
+             008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8\
+             997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83"
+        );
+    }
+
+    /// Key file for the key of [sign_transaction_eip155] encrypted with the password `oscoin`
+    /// using scrypt.
+    const SCRYPT_KEY_FILE: &str = r#"{
+        "version": 3,
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "101112131415161718191a1b1c1d1e1f" },
+            "ciphertext": "6b9fe48e1d61f49ece27db081223ca19cd9af3446254591bcb3a683d2bec383c",
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": 32,
+                "n": 1024,
+                "r": 8,
+                "p": 1,
+                "salt": "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f"
+            },
+            "mac": "0486b4ba3d79ec074dd2111b432fabef9b06792e466102c7ee1d1ab1711ed5e9"
+        }
+    }"#;
+
+    #[test]
+    fn import_scrypt_key() {
+        let mut keystore = Keystore::new();
+        let address = keystore.import_key_json(SCRYPT_KEY_FILE, "oscoin").unwrap();
+        assert_eq!(
+            address,
+            "9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn reject_expensive_key_files() {
+        let dev_key = include_str!("../../dev-node/dev-key.json");
+        let key_files = vec![
+            dev_key.replace("\"c\": 10240", "\"c\": 4294967295"),
+            dev_key.replace("\"dklen\": 32", "\"dklen\": 4294967295"),
+            SCRYPT_KEY_FILE.replace("\"n\": 1024", "\"n\": 1073741824"),
+            SCRYPT_KEY_FILE.replace("\"n\": 1024", "\"n\": 1000"),
+            SCRYPT_KEY_FILE.replace("\"r\": 8", "\"r\": 4294967295"),
+            SCRYPT_KEY_FILE.replace("\"p\": 1", "\"p\": 4294967295"),
+        ];
+        for key_file in key_files {
+            match Keystore::new().import_key_json(&key_file, "oscoin") {
+                Err(SignerError::InvalidKeyFile(_)) => (),
+                result => panic!("Unexpected result {:?}", result),
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Transports used by the client to talk to the node.
+use std::error;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use web3::futures::future::{Either, Future};
+use web3::transports::{EventLoopHandle, Http, Ipc, WebSocket};
+use web3::{RequestId, Transport};
+
+/// Describes how the client connects to the node.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Endpoint {
+    /// JSON-RPC over HTTP at the given URL, for example `http://localhost:8545`.
+    Http(String),
+    /// JSON-RPC over a WebSocket at the given URL, for example `ws://localhost:8546`.
+    WebSocket(String),
+    /// JSON-RPC over the IPC socket at the given path.
+    Ipc(PathBuf),
+}
+
+impl Endpoint {
+    /// Creates an endpoint from a URL. The transport is determined by the URL scheme:
+    /// `http://` and `https://` use HTTP, `ws://` and `wss://` use a WebSocket and `ipc://`
+    /// followed by a path uses the IPC socket at that path. An absolute path without a scheme
+    /// is also accepted as the path of an IPC socket.
+    ///
+    /// Returns an error for any other URL.
+    pub fn from_url(url: &str) -> Result<Endpoint, InvalidEndpointUrl> {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            Ok(Endpoint::Http(url.to_string()))
+        } else if url.starts_with("ws://") || url.starts_with("wss://") {
+            Ok(Endpoint::WebSocket(url.to_string()))
+        } else if url.starts_with("ipc://") && url.len() > "ipc://".len() {
+            Ok(Endpoint::Ipc(PathBuf::from(&url["ipc://".len()..])))
+        } else if Path::new(url).is_absolute() {
+            Ok(Endpoint::Ipc(PathBuf::from(url)))
+        } else {
+            Err(InvalidEndpointUrl(url.to_string()))
+        }
+    }
+}
+
+/// Error returned by [Endpoint::from_url] for URLs that do not describe a supported endpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvalidEndpointUrl(pub String);
+
+impl fmt::Display for InvalidEndpointUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid node URL {:?}: expected an http, https, ws, wss or ipc URL or an absolute \
+             IPC socket path",
+            self.0
+        )
+    }
+}
+
+impl error::Error for InvalidEndpointUrl {}
+
+/// [web3::Transport] that sends requests through one of the transports supported by
+/// [Endpoint] and fails requests that take longer than the configured timeout.
+#[derive(Clone)]
+pub struct NodeTransport {
+    inner: InnerTransport,
+    timeout: Option<Duration>,
+    timer: tokio_timer::Timer,
+}
+
+#[derive(Debug, Clone)]
+enum InnerTransport {
+    Http(Http),
+    WebSocket(WebSocket),
+    Ipc(Ipc),
+}
+
+impl NodeTransport {
+    /// Connects to `endpoint`. The returned [EventLoopHandle] runs the IO of the transport and
+    /// must be kept alive as long as the transport is used.
+    ///
+    /// Request timeouts are scheduled on `timer`. Its `max_timeout` must not be smaller than
+    /// `timeout`.
+    pub fn new(
+        endpoint: &Endpoint,
+        timeout: Option<Duration>,
+        timer: tokio_timer::Timer,
+    ) -> Result<(EventLoopHandle, NodeTransport), web3::Error> {
+        let (event_loop_handle, inner) = match endpoint {
+            Endpoint::Http(url) => {
+                let (handle, transport) = Http::new(url)?;
+                (handle, InnerTransport::Http(transport))
+            }
+            Endpoint::WebSocket(url) => {
+                let (handle, transport) = WebSocket::new(url)?;
+                (handle, InnerTransport::WebSocket(transport))
+            }
+            Endpoint::Ipc(path) => {
+                let (handle, transport) = Ipc::new(path)?;
+                (handle, InnerTransport::Ipc(transport))
+            }
+        };
+        let transport = NodeTransport {
+            inner,
+            timeout,
+            timer,
+        };
+        Ok((event_loop_handle, transport))
+    }
+}
+
+impl fmt::Debug for NodeTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeTransport")
+            .field("inner", &self.inner)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Transport for NodeTransport {
+    type Out = Box<dyn Future<Item = jsonrpc_core::Value, Error = web3::Error> + Send>;
+
+    fn prepare(
+        &self,
+        method: &str,
+        params: Vec<jsonrpc_core::Value>,
+    ) -> (RequestId, jsonrpc_core::Call) {
+        match &self.inner {
+            InnerTransport::Http(transport) => transport.prepare(method, params),
+            InnerTransport::WebSocket(transport) => transport.prepare(method, params),
+            InnerTransport::Ipc(transport) => transport.prepare(method, params),
+        }
+    }
+
+    fn send(&self, id: RequestId, request: jsonrpc_core::Call) -> Self::Out {
+        let response: Self::Out = match &self.inner {
+            InnerTransport::Http(transport) => Box::new(transport.send(id, request)),
+            InnerTransport::WebSocket(transport) => Box::new(transport.send(id, request)),
+            InnerTransport::Ipc(transport) => Box::new(transport.send(id, request)),
+        };
+        match self.timeout {
+            Some(timeout) => {
+                let response = response
+                    .select2(self.timer.sleep(timeout))
+                    .then(move |result| match result {
+                        Ok(Either::A((value, _))) => Ok(value),
+                        Err(Either::A((err, _))) => Err(err),
+                        Ok(Either::B(_)) => Err(web3::Error::Transport(format!(
+                            "Request timed out after {:?}",
+                            timeout
+                        ))),
+                        Err(Either::B((err, _))) => Err(web3::Error::Transport(format!(
+                            "Request timer failed: {}",
+                            err
+                        ))),
+                    });
+                Box::new(response)
+            }
+            None => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoint_from_url() {
+        assert_eq!(
+            Endpoint::from_url("http://localhost:8545"),
+            Ok(Endpoint::Http("http://localhost:8545".to_string()))
+        );
+        assert_eq!(
+            Endpoint::from_url("wss://node.example.com"),
+            Ok(Endpoint::WebSocket("wss://node.example.com".to_string()))
+        );
+        assert_eq!(
+            Endpoint::from_url("ipc:///var/run/parity.ipc"),
+            Ok(Endpoint::Ipc(PathBuf::from("/var/run/parity.ipc")))
+        );
+        assert_eq!(
+            Endpoint::from_url("/var/run/parity.ipc"),
+            Ok(Endpoint::Ipc(PathBuf::from("/var/run/parity.ipc")))
+        );
+        for url in &[
+            "localhost:8545",
+            "ftp://node.example.com",
+            "parity.ipc",
+            "ipc://",
+            "",
+        ] {
+            assert_eq!(
+                Endpoint::from_url(url),
+                Err(InvalidEndpointUrl(url.to_string()))
+            );
+        }
+    }
+}
@@ -3,22 +3,153 @@ use env_logger;
 use std::fs;
 
 use clap::crate_version;
-use clap::App;
+use clap::{App, Arg, ArgMatches, SubCommand};
 
 fn main() {
     env_logger::init();
 
-    let _matches = App::new("Oscoin Ledger Deployment")
+    let matches = App::new("Oscoin Ledger Deployment")
         .version(crate_version!())
         .max_term_width(80)
         .about(format!(
-            "\nDeploys the Wasm contract in {}. Outputs the address of ledger contract and writes it to {}",
+            "\nDeploys the Wasm contract in {} behind the proxy in {}. Outputs the address of ledger contract and writes it to {}",
             oscoin_deploy::CONTRACT_CODE_PATH,
+            oscoin_deploy::PROXY_CODE_PATH,
             oscoin_deploy::CONTRACT_ADDRESS_FILE
             ).as_ref())
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .env("OSC_DEPLOY_CONFIG")
+                .help("TOML or JSON file with the deploy configuration. Options given on the command line override the file")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("node-url")
+                .long("node-url")
+                .value_name("URL")
+                .env("OSC_DEPLOY_NODE_URL")
+                .help("URL of the node's HTTP JSON-RPC endpoint")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("contract-code")
+                .long("contract-code")
+                .value_name("FILE")
+                .env("OSC_DEPLOY_CONTRACT_CODE")
+                .help("Path to the ledger Wasm code")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("proxy-code")
+                .long("proxy-code")
+                .value_name("FILE")
+                .env("OSC_DEPLOY_PROXY_CODE")
+                .help("Path to the ledger proxy Wasm code")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("account")
+                .long("account")
+                .value_name("ADDRESS")
+                .env("OSC_DEPLOY_ACCOUNT")
+                .help("Hex address of the node account that deploys the ledger")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("password")
+                .long("password")
+                .value_name("PASSWORD")
+                .env("OSC_DEPLOY_PASSWORD")
+                .hide_env_values(true)
+                .help("Password to unlock the deploy account")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("deploy-gas")
+                .long("deploy-gas")
+                .value_name("GAS")
+                .env("OSC_DEPLOY_GAS")
+                .help("Maximum gas used to deploy a contract")
+                .takes_value(true)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("genesis")
+                .long("genesis")
+                .value_name("FILE")
+                .help("TOML or JSON file with the initial state of the ledger")
+                .takes_value(true),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about(
+                    format!(
+                        "Exports the state of the ledger at the address in {} to a JSON file.",
+                        oscoin_deploy::CONTRACT_ADDRESS_FILE
+                    )
+                    .as_ref(),
+                )
+                .arg(Arg::with_name("FILE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Deploys a new ledger with the state from a JSON file created by `export`.")
+                .arg(Arg::with_name("FILE").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("upgrade").about(
+                format!(
+                    "Deploys the Wasm contract in {} and upgrades the ledger at the address in {} to it. The ledger state is preserved.",
+                    oscoin_deploy::CONTRACT_CODE_PATH,
+                    oscoin_deploy::CONTRACT_ADDRESS_FILE
+                )
+                .as_ref(),
+            ),
+        )
         .get_matches();
 
-    let contract = oscoin_deploy::deploy().unwrap();
+    let config = deploy_config(&matches).unwrap();
+
+    if matches.subcommand_matches("upgrade").is_some() {
+        let proxy_address = oscoin_client::read_contract_address().unwrap();
+        let implementation =
+            oscoin_deploy::upgrade_with(&config, proxy_address.to_fixed_bytes().into()).unwrap();
+        println!(
+            "Ledger upgraded to implementation {}",
+            hex::encode(implementation)
+        );
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("export") {
+        let path = matches.value_of("FILE").unwrap();
+        let ledger_address = oscoin_client::read_contract_address().unwrap();
+        let snapshot =
+            oscoin_deploy::export_state_with(&config, ledger_address.to_fixed_bytes().into())
+                .unwrap();
+        oscoin_deploy::write_snapshot_file(path, &snapshot).unwrap();
+        println!("Exported {} projects to {}", snapshot.projects.len(), path);
+        return;
+    }
+
+    let genesis = if let Some(matches) = matches.subcommand_matches("import") {
+        oscoin_deploy::read_snapshot_file(matches.value_of("FILE").unwrap()).unwrap()
+    } else {
+        match matches.value_of("genesis") {
+            Some(path) => oscoin_deploy::read_genesis_file(path).unwrap(),
+            None => oscoin_deploy::GenesisConfig::default(),
+        }
+    };
+
+    let contract = oscoin_deploy::deploy_with(&config, &genesis).unwrap();
 
     let contract_address_hex = hex::encode(contract.address());
     println!("Ledger contract address: {}", contract_address_hex);
@@ -28,3 +159,36 @@ fn main() {
         oscoin_deploy::CONTRACT_ADDRESS_FILE
     );
 }
+
+/// Reads the deploy configuration from the `--config` file or uses the default configuration
+/// and applies the options given on the command line or as environment variables.
+fn deploy_config(matches: &ArgMatches) -> Result<oscoin_deploy::DeployConfig, String> {
+    let mut config = match matches.value_of("config") {
+        Some(path) => oscoin_deploy::read_deploy_config(path)?,
+        None => oscoin_deploy::DeployConfig::default(),
+    };
+    if let Some(node_url) = matches.value_of("node-url") {
+        config.node_url = node_url.to_string();
+    }
+    if let Some(path) = matches.value_of("contract-code") {
+        config.contract_code_path = path.into();
+    }
+    if let Some(path) = matches.value_of("proxy-code") {
+        config.proxy_code_path = path.into();
+    }
+    if let Some(account) = matches.value_of("account") {
+        config.account = account
+            .trim_start_matches("0x")
+            .parse()
+            .map_err(|e| format!("Invalid account address {}: {:?}", account, e))?;
+    }
+    if let Some(password) = matches.value_of("password") {
+        config.password = password.to_string();
+    }
+    if let Some(deploy_gas) = matches.value_of("deploy-gas") {
+        config.deploy_gas = deploy_gas
+            .parse()
+            .map_err(|e| format!("Invalid deploy gas {}: {}", deploy_gas, e))?;
+    }
+    Ok(config)
+}
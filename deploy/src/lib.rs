@@ -1,24 +1,38 @@
 //! Deploy the ledger Wasm contract to a node.
 //!
-//! All of the parameters are provided as constants.
+//! The node, the contract code and the deploying account are configured with a [DeployConfig].
+//! The defaults for the configuration are provided as constants. The initial state of the
+//! ledger can be configured with a [GenesisFile]. The ledger is deployed behind a proxy so that
+//! it can be upgraded with [upgrade] without losing its state.
 //!
 //! ```no_run
 //! let contract = oscoin_deploy::deploy().unwrap();
 //! oscoin_deploy::write_contract_address(&contract.address());
 //! ```
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
+use std::path::{Path, PathBuf};
 use web3::contract::{Contract, Options};
 use web3::futures::Future;
 use web3::types::Address;
 use web3::Web3;
 
+pub use oscoin_ledger::{GenesisConfig, GenesisProject, Oscoin, ProtocolParams, StateSnapshot};
+
 /// Maximum gas used to deploy the contract
 pub const DEPLOY_GAS: u32 = 100_000_000;
 
 /// Path to the contract Wasm code. Is `./target/oscoin_ledger_pwasm.wasm`.
 pub const CONTRACT_CODE_PATH: &str = "./target/oscoin_ledger_pwasm.wasm";
 
-/// Contract ABI JSON. This is empty because there are no constructor arguments.
+/// Path to the ledger proxy Wasm code. Is `./target/oscoin_ledger_proxy.wasm`. See
+/// `oscoin_ledger::proxy`.
+pub const PROXY_CODE_PATH: &str = "./target/oscoin_ledger_proxy.wasm";
+
+/// Contract ABI JSON. This is empty because the constructor input is not ABI encoded. Instead
+/// the constructor input is appended to the contract code.
 const CONTRACT_ABI: &[u8] = b"[]";
 
 /// Development account address for our custom chainspec.
@@ -27,37 +41,161 @@ pub const DEV_ACCOUNT_ADDR: &str = "bcd6e47db1ac1f7f021988e20854d27778de6e4d";
 /// Password for the dev account
 pub const DEV_ACCOUNT_PASSWORD: &str = "";
 
+/// URL of the node the ledger is deployed to by default.
 pub const NODE_URL: &str = "http://localhost:8545";
 
 /// File to write the address of the deployed contract to
 pub const CONTRACT_ADDRESS_FILE: &str = "./.oscoin_ledger_address";
 
-/// Deploys the contract.
+/// Configures where and how the ledger is deployed.
+///
+/// The [Default] configuration deploys the code from [CONTRACT_CODE_PATH] and [PROXY_CODE_PATH]
+/// to the node at [NODE_URL] with the dev account [DEV_ACCOUNT_ADDR]. A configuration can be
+/// read from a file with [read_deploy_config]. All fields are optional in the file.
 ///
-/// 1. Read the contract code from [CONTRACT_CODE_PATH].
-/// 2. Deploy the contract with [DEV_ACCOUNT_ADDR] as the sender.
+/// ```toml
+/// node_url = "http://staging.example.com:8545"
+/// account = "0xbcd6e47db1ac1f7f021988e20854d27778de6e4d"
+/// password = "secret"
+/// deploy_gas = 50000000
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeployConfig {
+    /// URL of the node's HTTP JSON-RPC endpoint.
+    pub node_url: String,
+    /// Path to the ledger Wasm code.
+    pub contract_code_path: PathBuf,
+    /// Path to the ledger proxy Wasm code.
+    pub proxy_code_path: PathBuf,
+    /// Account that sends the deploy transactions. The account must be managed by the node.
+    pub account: Address,
+    /// Password to unlock [DeployConfig::account] on the node.
+    pub password: String,
+    /// Maximum gas used to deploy a contract.
+    pub deploy_gas: u32,
+}
+
+impl Default for DeployConfig {
+    fn default() -> DeployConfig {
+        DeployConfig {
+            node_url: NODE_URL.to_string(),
+            contract_code_path: PathBuf::from(CONTRACT_CODE_PATH),
+            proxy_code_path: PathBuf::from(PROXY_CODE_PATH),
+            account: dev_account_address(),
+            password: DEV_ACCOUNT_PASSWORD.to_string(),
+            deploy_gas: DEPLOY_GAS,
+        }
+    }
+}
+
+/// Reads a [DeployConfig] from `path`. Fields missing from the file are set to the default.
+///
+/// Files with the `.toml` extension are parsed as TOML. All other files are parsed as JSON.
+pub fn read_deploy_config(path: impl AsRef<Path>) -> Result<DeployConfig, String> {
+    read_file(path.as_ref())
+}
+
+/// Deploys the ledger with the default [DeployConfig] and [GenesisConfig]. See [deploy_with].
 ///
 /// **Note:** This contract blocks on IO.
 pub fn deploy() -> Result<Contract<web3::transports::Http>, String> {
-    let web3 = prepare_web3();
+    deploy_with_genesis(&GenesisConfig::default())
+}
 
-    let contract_code = fs::read(CONTRACT_CODE_PATH)
-        .map_err(|e| format!("Failed to read {}: {}", CONTRACT_CODE_PATH, e))?;
+/// Deploys the ledger with the default [DeployConfig]. See [deploy_with].
+///
+/// **Note:** This contract blocks on IO.
+pub fn deploy_with_genesis(
+    genesis: &GenesisConfig,
+) -> Result<Contract<web3::transports::Http>, String> {
+    deploy_with(&DeployConfig::default(), genesis)
+}
+
+/// Deploys the ledger behind a proxy and initializes the ledger state with `genesis`. Returns
+/// the proxy contract. Clients use the address of the proxy to talk to the ledger.
+///
+/// 1. Deploy the ledger implementation from [DeployConfig::contract_code_path].
+/// 2. Deploy the proxy from [DeployConfig::proxy_code_path]. The address of the implementation
+///    and the serialized `genesis` are passed to the proxy constructor.
+///
+/// All contracts are deployed with [DeployConfig::account] as the sender which makes it the
+/// owner of the ledger if `genesis` has no owners.
+///
+/// **Note:** This contract blocks on IO.
+pub fn deploy_with(
+    config: &DeployConfig,
+    genesis: &GenesisConfig,
+) -> Result<Contract<web3::transports::Http>, String> {
+    let web3 = prepare_web3(config)?;
+    let implementation = deploy_implementation(config, &web3)?;
+
+    let mut proxy_code = read_code(&config.proxy_code_path)?;
+    proxy_code.extend_from_slice(implementation.address().as_bytes());
+    proxy_code.extend(genesis.serialize());
+    deploy_code(config, &web3, proxy_code)
+}
+
+/// Upgrades the ledger with the default [DeployConfig]. See [upgrade_with].
+///
+/// **Note:** This contract blocks on IO.
+pub fn upgrade(proxy_address: Address) -> Result<Address, String> {
+    upgrade_with(&DeployConfig::default(), proxy_address)
+}
+
+/// Deploys a new ledger implementation from [DeployConfig::contract_code_path] and upgrades the
+/// ledger behind the proxy at `proxy_address` to it. The ledger state is preserved. Returns the
+/// address of the new implementation.
+///
+/// [DeployConfig::account] must be an owner of the ledger.
+///
+/// **Note:** This contract blocks on IO.
+pub fn upgrade_with(config: &DeployConfig, proxy_address: Address) -> Result<Address, String> {
+    let web3 = prepare_web3(config)?;
+    let implementation = deploy_implementation(config, &web3)?;
+
+    let client = prepare_client(config, proxy_address)?;
+    client
+        .upgrade(
+            config.account.to_fixed_bytes().into(),
+            implementation.address().to_fixed_bytes(),
+        )
+        .map_err(|e| format!("Failed to upgrade the ledger: {:?}", e))?;
+    Ok(implementation.address())
+}
+
+/// Deploys the ledger code from [DeployConfig::contract_code_path]. The contract is only used as
+/// the implementation of a proxy.
+///
+/// The ledger constructor initializes the storage of the implementation contract with the
+/// default [GenesisConfig], which makes [DeployConfig::account] an owner of that storage. This
+/// state is separate from the ledger state because the proxy runs the implementation on the
+/// proxy storage.
+fn deploy_implementation(
+    config: &DeployConfig,
+    web3: &Web3<web3::transports::Http>,
+) -> Result<Contract<web3::transports::Http>, String> {
+    let contract_code = read_code(&config.contract_code_path)?;
+    deploy_code(config, web3, contract_code)
+}
 
+/// Deploys a contract with the given code and [DeployConfig::account] as the sender.
+///
+/// Parity passes any data following the Wasm module in `code` to the contract constructor.
+fn deploy_code(
+    config: &DeployConfig,
+    web3: &Web3<web3::transports::Http>,
+    code: Vec<u8>,
+) -> Result<Contract<web3::transports::Http>, String> {
     let builder = Contract::deploy(web3.eth(), CONTRACT_ABI)
         .expect("contract ABI is hardcoded and valid")
         .confirmations(0)
         .options(Options::with(|opt| {
-            opt.gas = Some(DEPLOY_GAS.into());
+            opt.gas = Some(config.deploy_gas.into());
         }));
 
     let pending_contract = builder
-        .sign_and_execute(
-            hex::encode(contract_code),
-            (),
-            dev_account_address(),
-            DEV_ACCOUNT_PASSWORD,
-        )
+        .sign_and_execute(hex::encode(code), (), config.account, &config.password)
         .expect("Correct parameters are passed to the constructor.");
 
     let contract = pending_contract
@@ -67,6 +205,133 @@ pub fn deploy() -> Result<Contract<web3::transports::Http>, String> {
     Ok(contract)
 }
 
+/// Genesis configuration of the ledger as read from a file by [read_genesis_file].
+///
+/// Addresses are hex strings with a `0x` prefix. All fields are optional.
+///
+/// ```toml
+/// owners = ["0xbcd6e47db1ac1f7f021988e20854d27778de6e4d"]
+///
+/// [balances]
+/// "0xbcd6e47db1ac1f7f021988e20854d27778de6e4d" = 1000
+///
+/// [[projects]]
+/// name = "monokol"
+/// description = "Looking glass into the future."
+/// img_url = "https://monok.el/img/logo.svg"
+/// members = ["0xbcd6e47db1ac1f7f021988e20854d27778de6e4d"]
+///
+/// [params]
+/// max_page_size = 50
+/// ```
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenesisFile {
+    pub owners: Vec<Address>,
+    pub balances: BTreeMap<Address, Oscoin>,
+    pub projects: Vec<GenesisFileProject>,
+    pub params: ProtocolParams,
+}
+
+/// Project entry of a [GenesisFile]. See [GenesisProject].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GenesisFileProject {
+    pub name: String,
+    pub description: String,
+    pub img_url: String,
+    pub members: Vec<Address>,
+}
+
+impl From<GenesisFile> for GenesisConfig {
+    fn from(file: GenesisFile) -> GenesisConfig {
+        GenesisConfig {
+            owners: file.owners.iter().map(|a| a.to_fixed_bytes()).collect(),
+            projects: file
+                .projects
+                .into_iter()
+                .map(|project| GenesisProject {
+                    name: project.name,
+                    description: project.description,
+                    img_url: project.img_url,
+                    members: project.members.iter().map(|a| a.to_fixed_bytes()).collect(),
+                    id: None,
+                })
+                .collect(),
+            balances: file
+                .balances
+                .iter()
+                .map(|(account, amount)| (account.to_fixed_bytes(), *amount))
+                .collect(),
+            params: file.params,
+            ..GenesisConfig::default()
+        }
+    }
+}
+
+/// Reads a [GenesisFile] from `path` and converts it into a [GenesisConfig].
+///
+/// Files with the `.toml` extension are parsed as TOML. All other files are parsed as JSON.
+pub fn read_genesis_file(path: impl AsRef<Path>) -> Result<GenesisConfig, String> {
+    let genesis_file: GenesisFile = read_file(path.as_ref())?;
+    Ok(genesis_file.into())
+}
+
+/// Reads a TOML file if `path` has the `.toml` extension and a JSON file otherwise.
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match path.extension() {
+        Some(ext) if ext == "toml" => toml::from_str(&content).map_err(|e| e.to_string()),
+        _ => serde_json::from_str(&content).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Exports the state of the ledger at `ledger_address` with the default [DeployConfig]. See
+/// [export_state_with].
+///
+/// **Note:** This contract blocks on IO.
+pub fn export_state(ledger_address: Address) -> Result<StateSnapshot, String> {
+    export_state_with(&DeployConfig::default(), ledger_address)
+}
+
+/// Exports the state of the ledger at `ledger_address` through the node at
+/// [DeployConfig::node_url]. See [oscoin_client::Client::export_state].
+///
+/// **Note:** This contract blocks on IO.
+pub fn export_state_with(
+    config: &DeployConfig,
+    ledger_address: Address,
+) -> Result<StateSnapshot, String> {
+    let client = prepare_client(config, ledger_address)?;
+    client
+        .export_state()
+        .map_err(|e| format!("Failed to export the ledger state: {:?}", e))
+}
+
+/// Writes `snapshot` as JSON to `path`.
+pub fn write_snapshot_file(path: impl AsRef<Path>, snapshot: &StateSnapshot) -> Result<(), String> {
+    let path = path.as_ref();
+    let content = serde_json::to_string_pretty(snapshot)
+        .map_err(|e| format!("Failed to serialize the snapshot: {}", e))?;
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Reads a JSON [StateSnapshot] from `path` and converts it into a [GenesisConfig] that
+/// recreates the state in a new ledger. See [StateSnapshot::into_genesis].
+pub fn read_snapshot_file(path: impl AsRef<Path>) -> Result<GenesisConfig, String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let snapshot: StateSnapshot = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    let version = snapshot.version;
+    snapshot
+        .into_genesis()
+        .ok_or_else(|| format!("Unsupported snapshot version {}", version))
+}
+
 /// Returns the address of the dev account provided by the `oscoin` chain spec.
 pub fn dev_account_address() -> Address {
     DEV_ACCOUNT_ADDR
@@ -80,9 +345,26 @@ pub fn write_contract_address(address: &Address) -> std::io::Result<()> {
     fs::write(CONTRACT_ADDRESS_FILE, contract_address_hex)
 }
 
-fn prepare_web3() -> Web3<web3::transports::Http> {
-    let (eloop, http) = web3::transports::Http::new(NODE_URL).expect("URL is hardcoded and valid");
+fn read_code(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn prepare_web3(config: &DeployConfig) -> Result<Web3<web3::transports::Http>, String> {
+    let (eloop, http) = web3::transports::Http::new(&config.node_url)
+        .map_err(|e| format!("Failed to connect to {}: {}", config.node_url, e))?;
     // run the event loop in the background
     eloop.into_remote();
-    web3::Web3::new(http)
+    Ok(web3::Web3::new(http))
+}
+
+fn prepare_client(
+    config: &DeployConfig,
+    ledger_address: Address,
+) -> Result<oscoin_client::blocking::Client, String> {
+    oscoin_client::ClientBuilder::new(ledger_address.to_fixed_bytes().into())
+        .node_url(&config.node_url)
+        .node_signer_password(&config.password)
+        .build()
+        .map(oscoin_client::blocking::Client::from)
+        .map_err(|e| format!("Failed to connect to {}: {}", config.node_url, e))
 }
@@ -0,0 +1,11 @@
+#![no_std]
+
+#[no_mangle]
+pub fn call() {
+    oscoin_ledger::proxy::call();
+}
+
+#[no_mangle]
+pub fn deploy() {
+    oscoin_ledger::proxy::deploy();
+}
@@ -6,4 +6,6 @@ pub fn call() {
 }
 
 #[no_mangle]
-pub fn deploy() {}
+pub fn deploy() {
+    oscoin_ledger::deploy();
+}
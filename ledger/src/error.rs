@@ -0,0 +1,96 @@
+//! Errors returned by [crate::interface::Ledger] methods.
+//!
+//! The error types mirror the errors described in the `ledger-spec` crate.
+use serde::{Deserialize, Serialize};
+
+/// Error returned by all [crate::interface::Ledger] methods.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum LedgerError {
+    /// The input to the ledger contract could not be decoded as a [crate::interface::Call] or the
+    /// arguments of the call are invalid.
+    InvalidCall,
+    /// Data in the ledger storage could not be decoded. This indicates a bug in the ledger.
+    InvalidStorage,
+    /// There is no project with the given ID.
+    ProjectNotFound,
+    /// The project cannot be unregistered because other projects depend on it.
+    ProjectHasDependents,
+    /// The sender is not a member of the project but the operation requires it.
+    NotAProjectMember,
+    /// The sender is not an owner of the ledger but the operation requires it. See
+    /// [crate::interface::Ledger::pause].
+    NotAnOwner,
+    /// The ledger is paused and does not accept updates. See
+    /// [crate::interface::Ledger::pause].
+    Paused,
+    /// The ledger was not deployed behind a proxy and cannot be upgraded. See
+    /// [crate::interface::Ledger::upgrade].
+    NotUpgradable,
+    /// The [crate::interface::GenesisConfig] passed when deploying the ledger could not be
+    /// decoded or is invalid.
+    InvalidGenesis,
+    /// The response to the call is larger than [crate::proxy::MAX_RESPONSE_SIZE] and cannot be
+    /// returned by the ledger proxy.
+    ResponseTooLarge,
+    Transfer(TransferError),
+    Keyset(KeysetError),
+    Checkpoint(CheckpointError),
+}
+
+/// Reasons for rejecting a transfer of Oscoin.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum TransferError {
+    /// The balance of the sender is smaller than the amount to transfer.
+    InsufficientBalance,
+    /// The balance of the receiver would exceed the maximum amount of Oscoin.
+    BalanceOverflow,
+}
+
+/// Reasons for rejecting a change to the members of a project.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum KeysetError {
+    /// Removing the last member of a project is not allowed.
+    LastMemberRemoved,
+}
+
+/// Reasons for rejecting a checkpoint.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum CheckpointError {
+    /// The contributions are not hash-linked as described in
+    /// [crate::interface::Checkpoint::contributions].
+    ContributionsNotHashLinked,
+    /// A dependency update adds a dependency the project already uses.
+    UsedDependencyAdded,
+    /// A dependency update removes a dependency the project does not use.
+    UnusedDependencyRemoved,
+    /// The dependency updates contain more than one update for the same project.
+    DuplicateDependencies,
+    /// A dependency update adds the checkpointed project as its own dependency.
+    SelfDependency,
+    /// A dependency update adds a project that is not registered.
+    UnknownDependency,
+}
+
+impl From<serde_cbor::Error> for LedgerError {
+    fn from(_: serde_cbor::Error) -> LedgerError {
+        LedgerError::InvalidStorage
+    }
+}
+
+impl From<TransferError> for LedgerError {
+    fn from(err: TransferError) -> LedgerError {
+        LedgerError::Transfer(err)
+    }
+}
+
+impl From<KeysetError> for LedgerError {
+    fn from(err: KeysetError) -> LedgerError {
+        LedgerError::Keyset(err)
+    }
+}
+
+impl From<CheckpointError> for LedgerError {
+    fn from(err: CheckpointError) -> LedgerError {
+        LedgerError::Checkpoint(err)
+    }
+}
@@ -5,12 +5,34 @@
 //! Calls to the ledger a reified in the [Call] enum. Each ledger method has a corresponding
 //! [Query] or [Update] constructor. With [dispatch] the method corresponding to a given [Call] is
 //! called on a [Ledger] implementation.
-use crate::pwasm::String;
+//!
+//! All [Ledger] methods return a [LedgerError] if the call fails. Implementations must not change
+//! the ledger state if they return an error.
+use crate::error::LedgerError;
+use crate::pwasm::{String, H256};
 use alloc::prelude::v1::Vec;
 use serde::{Deserialize, Serialize};
 
 pub type ProjectId = [u8; 20];
 pub type AccountId = [u8; 20];
+pub type Hash = [u8; 32];
+
+/// Amount of Oscoin.
+///
+/// This is a `u64` and not a `u128` because CBOR cannot encode integers larger than
+/// `u64::max_value()`.
+pub type Oscoin = u64;
+
+/// State of an account in the ledger.
+///
+/// Every address has an account. If the account has never been used it is the default account
+/// with zero nonce and zero balance.
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct Account {
+    /// Number of transfers that have been sent from this account.
+    pub nonce: u64,
+    pub balance: Oscoin,
+}
 
 #[derive(Serialize, Deserialize, Debug, Ord, Eq, PartialEq, PartialOrd, Clone)]
 pub struct Project {
@@ -21,45 +43,456 @@ pub struct Project {
     pub members: Vec<AccountId>,
 }
 
-#[derive(Serialize, Deserialize, Default, Clone)]
-pub struct ProjectList {
-    pub(crate) projects: Vec<Project>,
+/// A contribution to a project. Contributions are submitted with a [Checkpoint].
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct Contribution {
+    /// Hash of the preceding contribution.
+    pub prev: Hash,
+    /// Hash of the commit that makes up the contribution.
+    pub commit: Hash,
+    pub author: AccountId,
+    /// Reviewer or approver of the contribution.
+    pub signoff: Option<AccountId>,
+}
+
+/// Snapshot of a project’s state recorded in the ledger.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct Checkpoint {
+    /// Hash of the project source at the checkpoint.
+    pub project_hash: Hash,
+    /// Contributions since the previous checkpoint.
+    ///
+    /// The contributions form a hash-linked list: The `prev` hash of each contribution is the
+    /// `commit` hash of the preceding contribution. The `prev` hash of the first contribution
+    /// is the `commit` hash of the last contribution in the previous checkpoints. For the first
+    /// contribution of a project this is the zero hash.
+    pub contributions: Vec<Contribution>,
+    /// Changes to the project’s dependencies. They are applied from left to right.
+    pub dependency_updates: Vec<DependencyUpdate>,
 }
 
-impl ProjectList {
-    pub fn new() -> ProjectList {
-        ProjectList {
+/// Change to the dependencies of a project that is submitted with a [Checkpoint].
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum DependencyUpdate {
+    /// Adds the project to the dependencies. The project must be registered and must not be the
+    /// project that is checkpointed.
+    Depend { project_id: ProjectId },
+    /// Removes the project from the dependencies.
+    Undepend { project_id: ProjectId },
+}
+
+impl DependencyUpdate {
+    /// Returns the project that is added or removed as a dependency.
+    pub fn project_id(&self) -> &ProjectId {
+        match self {
+            DependencyUpdate::Depend { project_id } => project_id,
+            DependencyUpdate::Undepend { project_id } => project_id,
+        }
+    }
+}
+
+/// Change of the ledger state emitted by a ledger method.
+///
+/// Events are emitted as log entries with [LedgerEvent::topics] and the CBOR encoded event as data
+/// after the state has been changed. A method that returns an error does not emit any events.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub enum LedgerEvent {
+    /// The counter was incremented to `value`.
+    CounterIncremented {
+        value: u32,
+    },
+    ProjectRegistered {
+        project_id: ProjectId,
+        creator: AccountId,
+    },
+    ProjectUnregistered {
+        project_id: ProjectId,
+    },
+    MemberAdded {
+        project_id: ProjectId,
+        account: AccountId,
+    },
+    MemberRemoved {
+        project_id: ProjectId,
+        account: AccountId,
+    },
+    /// A checkpoint with the given index was added to the project. See
+    /// [Ledger::get_checkpoint].
+    CheckpointAdded {
+        project_id: ProjectId,
+        index: u64,
+    },
+    DependencyAdded {
+        project_id: ProjectId,
+        dependency_id: ProjectId,
+    },
+    DependencyRemoved {
+        project_id: ProjectId,
+        dependency_id: ProjectId,
+    },
+    /// New Oscoin were created. See [crate::Ledger_::mint].
+    Minted {
+        to: AccountId,
+        amount: Oscoin,
+    },
+    Transferred {
+        from: AccountId,
+        to: AccountId,
+        amount: Oscoin,
+    },
+    /// The ledger was paused by `owner`. See [Ledger::pause].
+    Paused {
+        owner: AccountId,
+    },
+    /// The ledger was unpaused by `owner`. See [Ledger::unpause].
+    Unpaused {
+        owner: AccountId,
+    },
+    /// `from` transferred its ownership of the ledger to `to`. See
+    /// [Ledger::transfer_ownership].
+    OwnershipTransferred {
+        from: AccountId,
+        to: AccountId,
+    },
+    /// The ledger proxy now forwards calls to the contract at `implementation`. See
+    /// [Ledger::upgrade].
+    Upgraded {
+        implementation: AccountId,
+    },
+}
+
+impl LedgerEvent {
+    /// Name of the event variant.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerEvent::CounterIncremented { .. } => "CounterIncremented",
+            LedgerEvent::ProjectRegistered { .. } => "ProjectRegistered",
+            LedgerEvent::ProjectUnregistered { .. } => "ProjectUnregistered",
+            LedgerEvent::MemberAdded { .. } => "MemberAdded",
+            LedgerEvent::MemberRemoved { .. } => "MemberRemoved",
+            LedgerEvent::CheckpointAdded { .. } => "CheckpointAdded",
+            LedgerEvent::DependencyAdded { .. } => "DependencyAdded",
+            LedgerEvent::DependencyRemoved { .. } => "DependencyRemoved",
+            LedgerEvent::Minted { .. } => "Minted",
+            LedgerEvent::Transferred { .. } => "Transferred",
+            LedgerEvent::Paused { .. } => "Paused",
+            LedgerEvent::Unpaused { .. } => "Unpaused",
+            LedgerEvent::OwnershipTransferred { .. } => "OwnershipTransferred",
+            LedgerEvent::Upgraded { .. } => "Upgraded",
+        }
+    }
+
+    /// Topics of the log entry the event is emitted with.
+    ///
+    /// The first topic is [crate::LEDGER_EVENT_TOPIC]. It allows clients to filter for ledger
+    /// events. The second topic is the Keccak hash of the event [LedgerEvent::name]. It allows
+    /// clients to filter for specific events.
+    pub fn topics(&self) -> [H256; 2] {
+        [
+            H256::from(crate::LEDGER_EVENT_TOPIC),
+            pwasm_std::keccak(self.name()),
+        ]
+    }
+}
+
+/// Maximum number of projects [Ledger::list_projects] looks at in one call unless
+/// [ProtocolParams::max_page_size] is configured differently.
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Initial state of the ledger. It is passed to the ledger contract when the contract is
+/// deployed. See [crate::Ledger_::deploy].
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+#[serde(default)]
+pub struct GenesisConfig {
+    /// Owners of the ledger. They may pause the ledger. See [Ledger::pause].
+    ///
+    /// If no owners are given the account deploying the ledger becomes the only owner. Must not
+    /// contain duplicates.
+    pub owners: Vec<AccountId>,
+    /// Projects that are registered when the ledger is deployed.
+    pub projects: Vec<GenesisProject>,
+    /// Oscoin that are minted to the given accounts when the ledger is deployed. Every account
+    /// must be listed at most once.
+    pub balances: Vec<(AccountId, Oscoin)>,
+    pub params: ProtocolParams,
+    /// Initial value of the counter.
+    pub counter: u32,
+}
+
+/// A project registered by [GenesisConfig].
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct GenesisProject {
+    pub name: String,
+    pub description: String,
+    pub img_url: String,
+    /// Members of the project. The first member is considered the creator of the project and
+    /// determines the project ID like [Ledger::register_project] does unless `id` is given.
+    /// Must not be empty or contain duplicates.
+    pub members: Vec<AccountId>,
+    /// ID of the project. This is used to import projects from a [StateSnapshot]. Must not be
+    /// used by another project.
+    #[serde(default)]
+    pub id: Option<ProjectId>,
+}
+
+/// Version of the [StateSnapshot] format.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Part of the ledger state returned by [Ledger::export_state].
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct StatePage {
+    pub counter: u32,
+    pub projects: Vec<Project>,
+    /// Cursor to pass to [Ledger::export_state] to get the next page. `None` if this is the last
+    /// page.
+    pub next_cursor: Option<u64>,
+}
+
+/// Ledger state assembled from all [StatePage]s. The snapshot can be imported into a new
+/// ledger with [StateSnapshot::into_genesis].
+///
+/// Only the counter and the projects with their members are included. Checkpoints,
+/// dependencies and accounts are not.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+pub struct StateSnapshot {
+    /// Format version of the snapshot. Is [SNAPSHOT_VERSION] for new snapshots.
+    pub version: u32,
+    pub counter: u32,
+    pub projects: Vec<Project>,
+}
+
+impl StateSnapshot {
+    /// Creates an empty snapshot with the current [SNAPSHOT_VERSION].
+    pub fn new() -> StateSnapshot {
+        StateSnapshot {
+            version: SNAPSHOT_VERSION,
+            counter: 0,
             projects: Vec::new(),
         }
     }
 
-    pub fn insert(&mut self, project: Project) {
-        self.projects.push(project)
+    /// Adds the content of `page` to the snapshot.
+    pub fn add_page(&mut self, page: StatePage) {
+        self.counter = page.counter;
+        self.projects.extend(page.projects);
     }
 
-    pub fn from_vec(vec: Vec<Project>) -> ProjectList {
-        ProjectList { projects: vec }
+    /// Returns a [GenesisConfig] that recreates the state of the snapshot in a new ledger.
+    /// Projects keep their IDs.
+    ///
+    /// Returns `None` if the snapshot version is not supported.
+    pub fn into_genesis(self) -> Option<GenesisConfig> {
+        if self.version != SNAPSHOT_VERSION {
+            return None;
+        }
+        let projects = self
+            .projects
+            .into_iter()
+            .map(|project| GenesisProject {
+                name: project.name,
+                description: project.description,
+                img_url: project.img_url,
+                members: project.members,
+                id: Some(project.id),
+            })
+            .collect();
+        Some(GenesisConfig {
+            projects,
+            counter: self.counter,
+            ..GenesisConfig::default()
+        })
+    }
+}
+
+impl Default for StateSnapshot {
+    fn default() -> StateSnapshot {
+        StateSnapshot::new()
+    }
+}
+
+/// Parameters of the ledger protocol. They are set with [GenesisConfig] when the ledger is
+/// deployed.
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Clone)]
+#[serde(default)]
+pub struct ProtocolParams {
+    /// Maximum number of projects [Ledger::list_projects] looks at in one call.
+    pub max_page_size: u64,
+}
+
+impl Default for ProtocolParams {
+    fn default() -> ProtocolParams {
+        ProtocolParams {
+            max_page_size: MAX_PAGE_SIZE,
+        }
     }
+}
 
-    pub fn into_vec(self) -> Vec<Project> {
-        self.projects
+/// Restricts the projects returned by [Ledger::list_projects]. A project must match all given
+/// criteria.
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct ProjectFilter {
+    /// Only include projects the account is a member of.
+    pub member: Option<AccountId>,
+    /// Only include projects whose name starts with the prefix.
+    pub name_prefix: Option<String>,
+}
+
+impl ProjectFilter {
+    pub fn matches(&self, project: &Project) -> bool {
+        let member_matches = match self.member {
+            Some(member) => project.members.contains(&member),
+            None => true,
+        };
+        let name_matches = match &self.name_prefix {
+            Some(prefix) => project.name.starts_with(prefix.as_str()),
+            None => true,
+        };
+        member_matches && name_matches
     }
 }
 
+/// A page of projects returned by [Ledger::list_projects].
+#[derive(Serialize, Deserialize, Debug, Default, Eq, PartialEq, Clone)]
+pub struct ProjectPage {
+    pub projects: Vec<Project>,
+    /// Offset to pass to [Ledger::list_projects] to get the next page. `None` if this is the
+    /// last page.
+    pub next_offset: Option<u64>,
+}
+
 /// Public interface of the oscoin ledger
 pub trait Ledger {
-    fn ping(&mut self) -> String;
+    fn ping(&mut self) -> Result<String, LedgerError>;
+
+    fn counter_inc(&mut self) -> Result<(), LedgerError>;
+
+    fn counter_value(&mut self) -> Result<u32, LedgerError>;
+
+    fn register_project(
+        &mut self,
+        name: String,
+        description: String,
+        img_url: String,
+    ) -> Result<ProjectId, LedgerError>;
+
+    fn get_project(&mut self, project_id: ProjectId) -> Result<Option<Project>, LedgerError>;
 
-    fn counter_inc(&mut self);
+    /// Returns a page of the registered projects that match `filter`.
+    ///
+    /// At most `limit` projects, but no more than [ProtocolParams::max_page_size], are looked
+    /// at starting from `offset`. Since only the projects that match the filter are returned a
+    /// page may contain fewer than `limit` projects even if it is not the last page. The first
+    /// page has offset zero. Subsequent pages are obtained by passing [ProjectPage::next_offset].
+    /// A `limit` of zero is rejected with [LedgerError::InvalidCall].
+    ///
+    /// A page also ends early if its serialized size would exceed
+    /// [crate::proxy::MAX_RESPONSE_SIZE].
+    ///
+    /// If the filter has a `member` the offset refers to the projects of that member. Otherwise
+    /// it refers to all projects. The order of the projects is unspecified and changes when a
+    /// project is unregistered.
+    fn list_projects(
+        &mut self,
+        offset: u64,
+        limit: u64,
+        filter: ProjectFilter,
+    ) -> Result<ProjectPage, LedgerError>;
 
-    fn counter_value(&mut self) -> u32;
+    /// Removes the project from the ledger. Afterwards [Ledger::get_project] returns `None` for
+    /// the project and it is not included in [Ledger::list_projects]. The checkpoints and
+    /// dependencies of the project are removed as well.
+    ///
+    /// The sender must be a member of the project. A project cannot be unregistered while other
+    /// projects depend on it.
+    fn unregister_project(&mut self, project_id: ProjectId) -> Result<(), LedgerError>;
 
-    fn register_project(&mut self, name: String, description: String, img_url: String)
-        -> ProjectId;
+    /// Adds `account` to the members of the project. Does nothing if `account` is already a
+    /// member.
+    ///
+    /// The sender must be a member of the project.
+    fn add_key(&mut self, project_id: ProjectId, account: AccountId) -> Result<(), LedgerError>;
 
-    fn get_project(&mut self, project_id: ProjectId) -> Option<Project>;
+    /// Removes `account` from the members of the project. Does nothing if `account` is not a
+    /// member.
+    ///
+    /// The sender must be a member of the project. The last member of a project cannot be
+    /// removed.
+    fn remove_key(&mut self, project_id: ProjectId, account: AccountId) -> Result<(), LedgerError>;
 
-    fn list_projects(&mut self) -> ProjectList;
+    /// Adds a [Checkpoint] to the project’s checkpoint history.
+    ///
+    /// The sender must be a member of the project. The contributions must be hash-linked as
+    /// described in [Checkpoint::contributions]. The dependency updates are applied to the
+    /// project’s dependencies. If one of them is invalid (see
+    /// [crate::error::CheckpointError]) the checkpoint is rejected as a whole.
+    fn checkpoint(
+        &mut self,
+        project_id: ProjectId,
+        project_hash: Hash,
+        contributions: Vec<Contribution>,
+        dependency_updates: Vec<DependencyUpdate>,
+    ) -> Result<(), LedgerError>;
+
+    /// Returns the checkpoint of the project with the given zero-based index.
+    ///
+    /// Returns `None` if the project does not exist or if it has not been checkpointed that many
+    /// times.
+    fn get_checkpoint(
+        &mut self,
+        project_id: ProjectId,
+        index: u64,
+    ) -> Result<Option<Checkpoint>, LedgerError>;
+
+    /// Returns the projects the given project currently depends on.
+    fn get_dependencies(&mut self, project_id: ProjectId) -> Result<Vec<ProjectId>, LedgerError>;
+
+    /// Returns the projects that currently depend on the given project.
+    fn get_dependents(&mut self, project_id: ProjectId) -> Result<Vec<ProjectId>, LedgerError>;
+
+    /// Transfers `amount` Oscoin from the sender’s account to `to`.
+    ///
+    /// The sender’s balance must be at least `amount` and the balance of `to` must not exceed the
+    /// maximum amount of Oscoin after the transfer.
+    fn transfer(&mut self, to: AccountId, amount: Oscoin) -> Result<(), LedgerError>;
+
+    /// Returns the [Account] for the given address.
+    fn get_account(&mut self, address: AccountId) -> Result<Account, LedgerError>;
+
+    /// Pauses the ledger. While the ledger is paused [dispatch] rejects all updates except
+    /// [Update::Unpause] with [LedgerError::Paused]. Queries are still answered.
+    ///
+    /// The sender must be an owner of the ledger. Does nothing if the ledger is already paused.
+    fn pause(&mut self) -> Result<(), LedgerError>;
+
+    /// Resumes accepting updates after [Ledger::pause].
+    ///
+    /// The sender must be an owner of the ledger. Does nothing if the ledger is not paused.
+    fn unpause(&mut self) -> Result<(), LedgerError>;
+
+    /// Replaces the sender in the set of ledger owners with `new_owner`.
+    ///
+    /// The sender must be an owner of the ledger. If `new_owner` already is an owner the sender
+    /// is just removed from the owners.
+    fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), LedgerError>;
+
+    fn is_paused(&mut self) -> Result<bool, LedgerError>;
+
+    /// Returns the owners of the ledger.
+    fn get_owners(&mut self) -> Result<Vec<AccountId>, LedgerError>;
+
+    /// Returns a page of the ledger state to build a [StateSnapshot]. The first page has cursor
+    /// zero. Subsequent pages are obtained by passing [StatePage::next_cursor].
+    ///
+    /// Pages are not consistent with each other if the ledger changes between calls. Pause the
+    /// ledger before exporting the state to get a consistent snapshot.
+    fn export_state(&mut self, cursor: u64) -> Result<StatePage, LedgerError>;
+
+    /// Makes the ledger proxy forward all calls to the ledger contract at `implementation`. The
+    /// ledger state is kept and migrated by the new implementation. See [crate::proxy].
+    ///
+    /// The sender must be an owner of the ledger. Fails with [LedgerError::NotUpgradable] if
+    /// the ledger was not deployed behind a proxy.
+    fn upgrade(&mut self, implementation: AccountId) -> Result<(), LedgerError>;
 }
 
 /// Represents a call to a ledger method. Either a [Query] or a [Update].
@@ -80,8 +513,32 @@ pub enum Call {
 pub enum Query {
     Ping,
     CounterValue,
-    GetProject { project_id: ProjectId },
-    ListProjects,
+    GetProject {
+        project_id: ProjectId,
+    },
+    ListProjects {
+        offset: u64,
+        limit: u64,
+        filter: ProjectFilter,
+    },
+    GetCheckpoint {
+        project_id: ProjectId,
+        index: u64,
+    },
+    GetDependencies {
+        project_id: ProjectId,
+    },
+    GetDependents {
+        project_id: ProjectId,
+    },
+    GetAccount {
+        address: AccountId,
+    },
+    IsPaused,
+    GetOwners,
+    ExportState {
+        cursor: u64,
+    },
 }
 
 /// Reified update to the ledger
@@ -95,6 +552,35 @@ pub enum Update {
         description: String,
         img_url: String,
     },
+    UnregisterProject {
+        project_id: ProjectId,
+    },
+    AddKey {
+        project_id: ProjectId,
+        account: AccountId,
+    },
+    RemoveKey {
+        project_id: ProjectId,
+        account: AccountId,
+    },
+    Checkpoint {
+        project_id: ProjectId,
+        project_hash: Hash,
+        contributions: Vec<Contribution>,
+        dependency_updates: Vec<DependencyUpdate>,
+    },
+    Transfer {
+        to: AccountId,
+        amount: Oscoin,
+    },
+    Pause,
+    Unpause,
+    TransferOwnership {
+        new_owner: AccountId,
+    },
+    Upgrade {
+        implementation: AccountId,
+    },
 }
 
 impl Call {
@@ -108,6 +594,16 @@ impl Call {
     }
 }
 
+impl GenesisConfig {
+    pub fn serialize(&self) -> Vec<u8> {
+        serde_cbor::to_vec(&self).expect("CBOR serialization to Vec always succeeds")
+    }
+
+    pub fn deserialize(data: &[u8]) -> serde_cbor::Result<Self> {
+        serde_cbor::from_slice(data)
+    }
+}
+
 impl From<Query> for Call {
     fn from(query: Query) -> Call {
         Call::Query(query)
@@ -122,22 +618,85 @@ impl From<Update> for Call {
 
 /// Calls the `ledger`’s method corresponding to `call` and returns the serialized result of the
 /// method call.
-pub fn dispatch(mut ledger: impl Ledger, call: Call) -> Vec<u8> {
-    let res = match call {
+///
+/// The result is a CBOR encoded `Result<T, LedgerError>` where `T` is the return type of the
+/// method.
+///
+/// While the ledger is paused all updates except [Update::Unpause] are rejected with
+/// [LedgerError::Paused] without calling the ledger method.
+pub fn dispatch(ledger: &mut impl Ledger, call: Call) -> Vec<u8> {
+    match call {
         Call::Query(query) => match query {
-            Query::Ping => serde_cbor::to_vec(&ledger.ping()),
-            Query::CounterValue => serde_cbor::to_vec(&ledger.counter_value()),
-            Query::GetProject { project_id } => serde_cbor::to_vec(&ledger.get_project(project_id)),
-            Query::ListProjects => serde_cbor::to_vec(&ledger.list_projects().into_vec()),
+            Query::Ping => encode_result(ledger.ping()),
+            Query::CounterValue => encode_result(ledger.counter_value()),
+            Query::GetProject { project_id } => encode_result(ledger.get_project(project_id)),
+            Query::ListProjects {
+                offset,
+                limit,
+                filter,
+            } => encode_result(ledger.list_projects(offset, limit, filter)),
+            Query::GetCheckpoint { project_id, index } => {
+                encode_result(ledger.get_checkpoint(project_id, index))
+            }
+            Query::GetDependencies { project_id } => {
+                encode_result(ledger.get_dependencies(project_id))
+            }
+            Query::GetDependents { project_id } => encode_result(ledger.get_dependents(project_id)),
+            Query::GetAccount { address } => encode_result(ledger.get_account(address)),
+            Query::IsPaused => encode_result(ledger.is_paused()),
+            Query::GetOwners => encode_result(ledger.get_owners()),
+            Query::ExportState { cursor } => encode_result(ledger.export_state(cursor)),
         },
-        Call::Update(update) => match update {
-            Update::CounterInc => serde_cbor::to_vec(&ledger.counter_inc()),
-            Update::RegisterProject {
-                name,
-                description,
-                img_url,
-            } => serde_cbor::to_vec(&ledger.register_project(name, description, img_url)),
+        Call::Update(Update::Unpause) => encode_result(ledger.unpause()),
+        Call::Update(update) => match ledger.is_paused() {
+            Ok(false) => dispatch_update(ledger, update),
+            Ok(true) => encode_result::<()>(Err(LedgerError::Paused)),
+            Err(err) => encode_result::<()>(Err(err)),
         },
-    };
-    res.expect("CBOR serialization never fails")
+    }
+}
+
+fn dispatch_update(ledger: &mut impl Ledger, update: Update) -> Vec<u8> {
+    match update {
+        Update::CounterInc => encode_result(ledger.counter_inc()),
+        Update::RegisterProject {
+            name,
+            description,
+            img_url,
+        } => encode_result(ledger.register_project(name, description, img_url)),
+        Update::UnregisterProject { project_id } => {
+            encode_result(ledger.unregister_project(project_id))
+        }
+        Update::AddKey {
+            project_id,
+            account,
+        } => encode_result(ledger.add_key(project_id, account)),
+        Update::RemoveKey {
+            project_id,
+            account,
+        } => encode_result(ledger.remove_key(project_id, account)),
+        Update::Checkpoint {
+            project_id,
+            project_hash,
+            contributions,
+            dependency_updates,
+        } => encode_result(ledger.checkpoint(
+            project_id,
+            project_hash,
+            contributions,
+            dependency_updates,
+        )),
+        Update::Transfer { to, amount } => encode_result(ledger.transfer(to, amount)),
+        Update::Pause => encode_result(ledger.pause()),
+        Update::Unpause => encode_result(ledger.unpause()),
+        Update::TransferOwnership { new_owner } => {
+            encode_result(ledger.transfer_ownership(new_owner))
+        }
+        Update::Upgrade { implementation } => encode_result(ledger.upgrade(implementation)),
+    }
+}
+
+/// Serializes the result of a ledger method call. See [dispatch].
+pub fn encode_result<T: Serialize>(result: Result<T, LedgerError>) -> Vec<u8> {
+    serde_cbor::to_vec(&result).expect("CBOR serialization never fails")
 }
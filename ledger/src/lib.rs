@@ -4,70 +4,289 @@ extern crate alloc;
 
 use alloc::prelude::v1::*;
 use alloc::vec;
+use core::cmp;
 
-use crate::pwasm::{Address, String};
+use crate::pwasm::String;
 
+pub mod error;
 pub mod interface;
+pub mod proxy;
 pub mod pwasm;
 pub mod storage;
 
-use interface::dispatch;
-pub use interface::{Call, Ledger, Project, ProjectId, ProjectList, Query, Update};
-use storage::Storage;
+pub use error::{CheckpointError, KeysetError, LedgerError, TransferError};
+use interface::{dispatch, encode_result};
+pub use interface::{
+    Account, AccountId, Call, Checkpoint, Contribution, DependencyUpdate, GenesisConfig,
+    GenesisProject, Hash, Ledger, LedgerEvent, Oscoin, Project, ProjectFilter, ProjectId,
+    ProjectPage, ProtocolParams, Query, StatePage, StateSnapshot, Update, MAX_PAGE_SIZE,
+    SNAPSHOT_VERSION,
+};
+use storage::{Encoding, Storage, StorageMap, StorageValue, StorageVec};
+
+/// Log topic under which the result of an [Update] is emitted. See [Ledger_::call].
+pub const UPDATE_RESULT_TOPIC: [u8; 32] = *b"oscoin_ledger::update_result\0\0\0\0";
+
+/// First log topic of all [LedgerEvent]s. See [LedgerEvent::topics].
+pub const LEDGER_EVENT_TOPIC: [u8; 32] = *b"oscoin_ledger::ledger_event\0\0\0\0\0";
+
+/// Version of the storage layout used by this version of the ledger.
+///
+/// When the storage layout changes this version must be increased and a migration from the
+/// previous version must be added to [Ledger_::migrate_from].
+pub const STORAGE_VERSION: u32 = 1;
+
+/// Upper bound for the serialized size of a page response without its projects. Used to keep
+/// pages below [proxy::MAX_RESPONSE_SIZE].
+const PAGE_OVERHEAD: usize = 64;
 
 pub fn call() {
-    let ledger = Ledger_::new(pwasm::Pwasm);
-    let call_result = Call::deserialize(pwasm_ethereum::input().as_slice());
-    let call = match call_result {
-        Ok(call) => call,
-        Err(err) => {
-            panic!("Failed to deserialize ledger call: {}", err);
-        }
-    };
-    let response = dispatch(ledger, call);
+    let mut ledger = Ledger_::new(pwasm::Pwasm);
+    let response = ledger.call(pwasm_ethereum::input().as_slice());
     pwasm_ethereum::ret(&response);
 }
 
+/// Entry point of the contract constructor. Initializes the ledger from the [GenesisConfig] in
+/// the constructor input. See [Ledger_::deploy].
+///
+/// Panics if the initialization fails so that the contract is not created.
+pub fn deploy() {
+    let mut ledger = Ledger_::new(pwasm::Pwasm);
+    if let Err(err) = ledger.deploy(pwasm_ethereum::input().as_slice()) {
+        panic!("Failed to deploy the ledger: {:?}", err);
+    }
+}
+
 /// Implements [Ledger] backed by [Storage].
-pub struct Ledger_ {
-    env: Box<dyn pwasm::Env>,
+///
+/// Methods validate their input before writing to storage so that no state is changed when an
+/// error is returned.
+pub struct Ledger_<E> {
+    env: E,
 }
 
-impl Ledger_ {
-    pub fn new(env: impl pwasm::Env + 'static) -> Ledger_ {
-        Ledger_ { env: Box::new(env) }
+impl<E: pwasm::Env> Ledger_<E> {
+    pub fn new(env: E) -> Ledger_<E> {
+        Ledger_ { env }
     }
 
-    fn storage(&mut self) -> Storage {
-        Storage::new(self.env.as_mut())
+    /// Gives access to the underlying environment. Tests use this to change the sender or to
+    /// inspect the state.
+    pub fn env_mut(&mut self) -> &mut E {
+        &mut self.env
     }
-}
 
-const COUNTER_KEY: &[u8] = b"counter";
-const PROJECTS_KEY: &[u8] = b"projects";
+    /// Creates `amount` new Oscoin and adds them to the balance of `address`.
+    ///
+    /// This is not exposed as a ledger method. It is used to set up the initial balances, for
+    /// example in tests. Returns [TransferError::BalanceOverflow] if the new balance would exceed
+    /// the maximum amount of Oscoin.
+    pub fn mint(&mut self, address: AccountId, amount: Oscoin) -> Result<(), LedgerError> {
+        let mut account = self.get_account(address)?;
+        account.balance = account
+            .balance
+            .checked_add(amount)
+            .ok_or(TransferError::BalanceOverflow)?;
+        accounts().insert(&mut self.storage(), &address, &account);
+        self.emit_event(&LedgerEvent::Minted {
+            to: address,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// Decodes `input` as a CBOR encoded [GenesisConfig] and initializes the ledger with it. If
+    /// `input` is empty the default [GenesisConfig] is used. If the configuration has no owners
+    /// the sender becomes the owner of the ledger. Configurations that list an owner, an account
+    /// with a balance or a member of a project more than once are rejected with
+    /// [LedgerError::InvalidGenesis].
+    ///
+    /// This must only be called once when the ledger is deployed. If an error is returned the
+    /// state may have been changed partially and the deployment must be aborted.
+    pub fn deploy(&mut self, input: &[u8]) -> Result<(), LedgerError> {
+        let genesis = if input.is_empty() {
+            GenesisConfig::default()
+        } else {
+            GenesisConfig::deserialize(input).map_err(|_| LedgerError::InvalidGenesis)?
+        };
+        if genesis.params.max_page_size == 0
+            || has_duplicates(&genesis.owners)
+            || has_duplicates(
+                &genesis
+                    .balances
+                    .iter()
+                    .map(|(account, _)| account)
+                    .collect::<Vec<_>>(),
+            )
+            || genesis
+                .projects
+                .iter()
+                .any(|project| project.members.is_empty() || has_duplicates(&project.members))
+        {
+            return Err(LedgerError::InvalidGenesis);
+        }
+
+        let owner_list = if genesis.owners.is_empty() {
+            vec![self.env.sender().to_fixed_bytes()]
+        } else {
+            genesis.owners
+        };
+        let storage = &mut self.storage();
+        storage_version().insert(storage, &STORAGE_VERSION);
+        owners().insert(storage, &owner_list);
+        protocol_params().insert(storage, &genesis.params);
+        if genesis.counter != 0 {
+            counter().insert(storage, &genesis.counter);
+        }
+        for (account, amount) in genesis.balances {
+            self.mint(account, amount).map_err(|err| match err {
+                LedgerError::Transfer(_) => LedgerError::InvalidGenesis,
+                err => err,
+            })?;
+        }
+        // Projects with a given ID are created first so that derived IDs cannot take them.
+        let (imported_projects, new_projects): (Vec<_>, Vec<_>) = genesis
+            .projects
+            .into_iter()
+            .partition(|project| project.id.is_some());
+        for project in imported_projects.into_iter().chain(new_projects) {
+            self.create_project(
+                project.id,
+                project.members[0],
+                project.name,
+                project.description,
+                project.img_url,
+                project.members,
+            )?;
+        }
+        Ok(())
+    }
 
-impl Ledger for Ledger_ {
-    fn ping(&mut self) -> String {
-        String::from("pong")
+    /// Decodes `input` as a [Call], dispatches it and returns the serialized result. See
+    /// [dispatch].
+    ///
+    /// The return value of a transaction is not available to clients. Thus the result of an
+    /// [Update] is also emitted as a log entry with the single topic [UPDATE_RESULT_TOPIC] and
+    /// the serialized result as data. This includes errors returned for inputs that cannot be
+    /// decoded and migration failures so that every transaction sent to the ledger has a result
+    /// log. Results of [Query] calls are not logged.
+    ///
+    /// Before the call is dispatched the storage is migrated with [Ledger_::migrate].
+    ///
+    /// Responses larger than [proxy::MAX_RESPONSE_SIZE] are replaced with
+    /// [LedgerError::ResponseTooLarge] so that every response can be returned by the proxy.
+    pub fn call(&mut self, input: &[u8]) -> Vec<u8> {
+        let (call, log_result) = match Call::deserialize(input) {
+            Ok(call @ Call::Query(_)) => (Ok(call), false),
+            Ok(call) => (Ok(call), true),
+            Err(_) => (Err(LedgerError::InvalidCall), true),
+        };
+        let response = match self.migrate().and(call) {
+            Ok(call) => dispatch(self, call),
+            Err(err) => encode_result::<()>(Err(err)),
+        };
+        let response = if response.len() > proxy::MAX_RESPONSE_SIZE {
+            encode_result::<()>(Err(LedgerError::ResponseTooLarge))
+        } else {
+            response
+        };
+        if log_result {
+            self.env
+                .log(&[pwasm::H256::from(UPDATE_RESULT_TOPIC)], &response);
+        }
+        response
     }
 
-    fn counter_inc(&mut self) {
-        let val: u32 = self.storage().read(COUNTER_KEY).unwrap().unwrap_or(0);
-        self.storage().write(COUNTER_KEY, &(val + 1));
+    /// Migrates the storage to [STORAGE_VERSION] if it was written by an older version of the
+    /// ledger. This is necessary after the ledger has been upgraded. See [Ledger::upgrade].
+    ///
+    /// Returns [LedgerError::InvalidStorage] if the storage was written by a newer version.
+    pub fn migrate(&mut self) -> Result<(), LedgerError> {
+        let version = storage_version().get(&mut self.storage())?.unwrap_or(0);
+        if version == STORAGE_VERSION {
+            return Ok(());
+        }
+        if version > STORAGE_VERSION {
+            return Err(LedgerError::InvalidStorage);
+        }
+        for from_version in version..STORAGE_VERSION {
+            self.migrate_from(from_version)?;
+        }
+        storage_version().insert(&mut self.storage(), &STORAGE_VERSION);
+        Ok(())
     }
 
-    fn counter_value(&mut self) -> u32 {
-        self.storage().read(COUNTER_KEY).unwrap().unwrap_or(0)
+    /// Migrates the storage from `version` to `version + 1`.
+    fn migrate_from(&mut self, version: u32) -> Result<(), LedgerError> {
+        match version {
+            // Ledgers deployed before the storage was versioned use the same layout as
+            // version 1.
+            0 => Ok(()),
+            _ => Err(LedgerError::InvalidStorage),
+        }
     }
 
-    fn register_project(
+    fn storage(&mut self) -> Storage {
+        Storage::new(&mut self.env)
+    }
+
+    /// Emits `event` as a log entry with [LedgerEvent::topics] and the CBOR encoded event as
+    /// data so that clients can follow changes to the ledger.
+    fn emit_event(&mut self, event: &LedgerEvent) {
+        let data = serde_cbor::to_vec(event).expect("CBOR serialization never fails");
+        self.env.log(&event.topics(), &data)
+    }
+
+    /// Returns the protocol parameters set when the ledger was deployed.
+    fn protocol_params(&mut self) -> Result<ProtocolParams, LedgerError> {
+        Ok(protocol_params()
+            .get(&mut self.storage())?
+            .unwrap_or_default())
+    }
+
+    /// Registers a new project created by `creator` with the given members.
+    ///
+    /// If `id` is `None` the ID is derived from `creator` and the number of projects they have
+    /// registered with [compute_project_id]. IDs that are already taken, for example by
+    /// imported projects, are skipped. If `id` is given and already taken
+    /// [LedgerError::InvalidGenesis] is returned.
+    fn create_project(
         &mut self,
+        id: Option<ProjectId>,
+        creator: AccountId,
         name: String,
         description: String,
         img_url: String,
-    ) -> ProjectId {
-        let id = compute_project_id(self.env.sender(), self.env.block_number());
-        let members = vec![self.env.sender().to_fixed_bytes()];
+        members: Vec<AccountId>,
+    ) -> Result<ProjectId, LedgerError> {
+        let mut registration_count = None;
+        let id = match id {
+            Some(id) => {
+                if projects().contains(&mut self.storage(), &id) {
+                    return Err(LedgerError::InvalidGenesis);
+                }
+                id
+            }
+            None => {
+                let mut count = registration_counts()
+                    .get(&mut self.storage(), &creator)?
+                    .unwrap_or(0);
+                loop {
+                    let id = compute_project_id(creator, count);
+                    count += 1;
+                    if !projects().contains(&mut self.storage(), &id) {
+                        registration_count = Some(count);
+                        break id;
+                    }
+                }
+            }
+        };
+        let mut updated_member_projects = Vec::new();
+        for member in members.iter() {
+            let mut project_ids = self.member_projects(member)?;
+            project_ids.push(id);
+            updated_member_projects.push((*member, project_ids));
+        }
         let project = Project {
             id,
             name,
@@ -76,37 +295,614 @@ impl Ledger for Ledger_ {
             members,
         };
 
-        let mut projects = self.list_projects();
-        ProjectList::insert(&mut projects, project.clone());
+        let storage = &mut self.storage();
+        let position = project_index().push(storage, &id)?;
+        projects().insert(storage, &id, &project);
+        project_positions().insert(storage, &id, &position);
+        for (member, project_ids) in updated_member_projects {
+            member_projects().insert(storage, &member, &project_ids);
+        }
+        if let Some(count) = registration_count {
+            registration_counts().insert(storage, &creator, &count);
+        }
+        self.emit_event(&LedgerEvent::ProjectRegistered {
+            project_id: id,
+            creator,
+        });
+
+        Ok(id)
+    }
 
-        self.storage().write(&id, &project);
-        self.storage().write(PROJECTS_KEY, &projects);
+    /// Returns the IDs of the projects `account` is a member of.
+    fn member_projects(&mut self, account: &AccountId) -> Result<Vec<ProjectId>, LedgerError> {
+        Ok(member_projects()
+            .get(&mut self.storage(), account)?
+            .unwrap_or_default())
+    }
 
-        id
+    /// Returns the number of registered projects.
+    fn project_count(&mut self) -> Result<u64, LedgerError> {
+        Ok(project_index().len(&mut self.storage())?)
     }
 
-    fn get_project(&mut self, account: ProjectId) -> Option<Project> {
-        self.storage().read::<Project>(&account).unwrap()
+    /// Returns the ID of the project at the given position of the project index.
+    fn indexed_project_id(&mut self, index: u64) -> Result<ProjectId, LedgerError> {
+        project_index()
+            .get(&mut self.storage(), index)?
+            .ok_or(LedgerError::InvalidStorage)
     }
 
-    fn list_projects(&mut self) -> ProjectList {
-        self.storage()
-            .read(PROJECTS_KEY)
-            .expect("Project list is successfully read from ledger")
-            .unwrap_or_default()
+    /// Returns the sender and the current owners if the sender is an owner of the ledger.
+    fn get_owners_as_owner(&mut self) -> Result<(AccountId, Vec<AccountId>), LedgerError> {
+        let sender = self.env.sender().to_fixed_bytes();
+        let owner_list = self.get_owners()?;
+        if !owner_list.contains(&sender) {
+            return Err(LedgerError::NotAnOwner);
+        }
+        Ok((sender, owner_list))
+    }
+
+    /// Returns the project with the given ID if the sender is one of its members.
+    fn get_project_as_member(&mut self, project_id: ProjectId) -> Result<Project, LedgerError> {
+        let project = self
+            .get_project(project_id)?
+            .ok_or(LedgerError::ProjectNotFound)?;
+        let sender = self.env.sender().to_fixed_bytes();
+        if !project.members.contains(&sender) {
+            return Err(LedgerError::NotAProjectMember);
+        }
+        Ok(project)
+    }
+}
+
+/// Error returned by [Ledger_::run_tx].
+#[cfg(any(feature = "std", test))]
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub enum TxError {
+    /// The transaction returned an error.
+    Ledger(LedgerError),
+    /// The transaction panicked. In a node this would fail the transaction.
+    Panicked,
+}
+
+#[cfg(any(feature = "std", test))]
+impl Ledger_<pwasm::TestEnv> {
+    /// Runs `tx` like a node would run a transaction signed by `sender` in the given block.
+    ///
+    /// If `tx` returns an error or panics all changes to the environment made by `tx` are
+    /// reverted.
+    pub fn run_tx<T>(
+        &mut self,
+        sender: pwasm::Address,
+        block_number: u64,
+        tx: impl FnOnce(&mut Self) -> Result<T, LedgerError>,
+    ) -> Result<T, TxError> {
+        self.env.sender = sender;
+        self.env.origin = sender;
+        self.env.block_number = block_number;
+        let snapshot = self.env.snapshot();
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tx(self)));
+        match result {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(err)) => {
+                self.env.revert(snapshot);
+                Err(TxError::Ledger(err))
+            }
+            Err(_) => {
+                self.env.revert(snapshot);
+                Err(TxError::Panicked)
+            }
+        }
     }
 }
 
-/// Computes the ID of a project registered by `creator` in the given block through a cryptographic
-/// hash.
+fn counter() -> StorageValue<u32> {
+    StorageValue::new(b"counter").with_encoding(Encoding::Compact)
+}
+
+/// Registered projects by ID.
+fn projects() -> StorageMap<ProjectId, Project> {
+    StorageMap::new(b"project")
+}
+
+/// IDs of all registered projects in no particular order. Used to list projects.
+fn project_index() -> StorageVec<ProjectId> {
+    StorageVec::new(b"project_index").with_encoding(Encoding::Compact)
+}
+
+/// Position of each project in [project_index].
+fn project_positions() -> StorageMap<ProjectId, u64> {
+    StorageMap::new(b"project_position").with_encoding(Encoding::Compact)
+}
+
+/// Checkpoints of a project in the order they were added.
+fn checkpoints(project_id: &ProjectId) -> StorageVec<Checkpoint> {
+    let mut prefix = Vec::from(b"checkpoint" as &[u8]);
+    prefix.extend_from_slice(project_id);
+    StorageVec::new(&prefix).with_encoding(Encoding::CompactCompressed)
+}
+
+/// The `commit` hash of the last contribution to each project.
+fn contributions_heads() -> StorageMap<ProjectId, Hash> {
+    StorageMap::new(b"contributions_head")
+}
+
+/// The projects each project depends on.
+fn dependencies() -> StorageMap<ProjectId, Vec<ProjectId>> {
+    StorageMap::new(b"dependencies")
+}
+
+/// The projects that depend on each project.
+fn dependents() -> StorageMap<ProjectId, Vec<ProjectId>> {
+    StorageMap::new(b"dependents")
+}
+
+fn accounts() -> StorageMap<AccountId, Account> {
+    StorageMap::new(b"account").with_encoding(Encoding::Compact)
+}
+
+/// Number of projects each address has registered. See [compute_project_id].
+fn registration_counts() -> StorageMap<AccountId, u64> {
+    StorageMap::new(b"registration_count").with_encoding(Encoding::Compact)
+}
+
+/// IDs of the projects each address is a member of.
+fn member_projects() -> StorageMap<AccountId, Vec<ProjectId>> {
+    StorageMap::new(b"member_projects")
+}
+
+/// Owners of the ledger. Initialized from [GenesisConfig::owners].
+fn owners() -> StorageValue<Vec<AccountId>> {
+    StorageValue::new(b"owners")
+}
+
+/// Version of the storage layout. See [STORAGE_VERSION].
+fn storage_version() -> StorageValue<u32> {
+    StorageValue::new(b"storage_version").with_encoding(Encoding::Compact)
+}
+
+/// Address of the ledger implementation the proxy forwards calls to. Only set if the ledger is
+/// deployed behind a proxy. See [proxy].
+fn implementation() -> StorageValue<AccountId> {
+    StorageValue::new(b"implementation").with_encoding(Encoding::Compact)
+}
+
+/// Whether the ledger is paused. See [Ledger::pause].
+fn paused() -> StorageValue<bool> {
+    StorageValue::new(b"paused").with_encoding(Encoding::Compact)
+}
+
+fn protocol_params() -> StorageValue<ProtocolParams> {
+    StorageValue::new(b"protocol_params").with_encoding(Encoding::Compact)
+}
+
+impl<E: pwasm::Env> Ledger for Ledger_<E> {
+    fn ping(&mut self) -> Result<String, LedgerError> {
+        Ok(String::from("pong"))
+    }
+
+    fn counter_inc(&mut self) -> Result<(), LedgerError> {
+        let value = counter().mutate(&mut self.storage(), |value| {
+            *value += 1;
+            *value
+        })?;
+        self.emit_event(&LedgerEvent::CounterIncremented { value });
+        Ok(())
+    }
+
+    fn counter_value(&mut self) -> Result<u32, LedgerError> {
+        Ok(counter().get(&mut self.storage())?.unwrap_or(0))
+    }
+
+    fn register_project(
+        &mut self,
+        name: String,
+        description: String,
+        img_url: String,
+    ) -> Result<ProjectId, LedgerError> {
+        let sender = self.env.sender().to_fixed_bytes();
+        self.create_project(None, sender, name, description, img_url, vec![sender])
+    }
+
+    fn get_project(&mut self, project_id: ProjectId) -> Result<Option<Project>, LedgerError> {
+        Ok(projects().get(&mut self.storage(), &project_id)?)
+    }
+
+    fn list_projects(
+        &mut self,
+        offset: u64,
+        limit: u64,
+        filter: ProjectFilter,
+    ) -> Result<ProjectPage, LedgerError> {
+        if limit == 0 {
+            return Err(LedgerError::InvalidCall);
+        }
+        let member_projects = match filter.member {
+            Some(member) => Some(self.member_projects(&member)?),
+            None => None,
+        };
+        let total = match &member_projects {
+            Some(project_ids) => project_ids.len() as u64,
+            None => self.project_count()?,
+        };
+        let max_page_size = self.protocol_params()?.max_page_size;
+        let mut end = cmp::min(total, offset.saturating_add(cmp::min(limit, max_page_size)));
+
+        let mut projects = Vec::new();
+        let mut response_size = PAGE_OVERHEAD;
+        for index in offset..end {
+            let project_id = match &member_projects {
+                Some(project_ids) => project_ids[index as usize],
+                None => self.indexed_project_id(index)?,
+            };
+            let project = self
+                .get_project(project_id)?
+                .ok_or(LedgerError::InvalidStorage)?;
+            if filter.matches(&project) {
+                // End the page early if the response would not fit through the proxy. A page
+                // always includes at least one project so that listing makes progress.
+                response_size += serde_cbor::to_vec(&project)?.len();
+                if response_size > proxy::MAX_RESPONSE_SIZE && !projects.is_empty() {
+                    end = index;
+                    break;
+                }
+                projects.push(project);
+            }
+        }
+
+        let next_offset = if end < total { Some(end) } else { None };
+        Ok(ProjectPage {
+            projects,
+            next_offset,
+        })
+    }
+
+    fn unregister_project(&mut self, project_id: ProjectId) -> Result<(), LedgerError> {
+        let project = self.get_project_as_member(project_id)?;
+        if !self.get_dependents(project_id)?.is_empty() {
+            return Err(LedgerError::ProjectHasDependents);
+        }
+
+        let position = project_positions()
+            .get(&mut self.storage(), &project_id)?
+            .ok_or(LedgerError::InvalidStorage)?;
+        let last_index = self
+            .project_count()?
+            .checked_sub(1)
+            .ok_or(LedgerError::InvalidStorage)?;
+        let last_project_id = self.indexed_project_id(last_index)?;
+        let mut updated_member_projects = Vec::new();
+        for member in project.members.iter() {
+            let mut project_ids = self.member_projects(member)?;
+            project_ids.retain(|id| *id != project_id);
+            updated_member_projects.push((*member, project_ids));
+        }
+        let mut updated_dependents = Vec::new();
+        for dependency_id in self.get_dependencies(project_id)? {
+            let mut dependency_dependents = self.get_dependents(dependency_id)?;
+            dependency_dependents.retain(|id| *id != project_id);
+            updated_dependents.push((dependency_id, dependency_dependents));
+        }
+
+        let storage = &mut self.storage();
+        checkpoints(&project_id).clear(storage)?;
+        // The last project of the index takes the place of the removed project.
+        project_index().swap_remove(storage, position)?;
+        if last_project_id != project_id {
+            project_positions().insert(storage, &last_project_id, &position);
+        }
+        project_positions().remove(storage, &project_id);
+        for (member, project_ids) in updated_member_projects {
+            member_projects().insert(storage, &member, &project_ids);
+        }
+        for (dependency_id, dependency_dependents) in updated_dependents {
+            dependents().insert(storage, &dependency_id, &dependency_dependents);
+        }
+        dependencies().remove(storage, &project_id);
+        dependents().remove(storage, &project_id);
+        contributions_heads().remove(storage, &project_id);
+        projects().remove(storage, &project_id);
+        self.emit_event(&LedgerEvent::ProjectUnregistered { project_id });
+        Ok(())
+    }
+
+    fn add_key(&mut self, project_id: ProjectId, account: AccountId) -> Result<(), LedgerError> {
+        let mut project = self.get_project_as_member(project_id)?;
+        if !project.members.contains(&account) {
+            let mut account_projects = self.member_projects(&account)?;
+            account_projects.push(project_id);
+            project.members.push(account);
+
+            let storage = &mut self.storage();
+            projects().insert(storage, &project_id, &project);
+            member_projects().insert(storage, &account, &account_projects);
+            self.emit_event(&LedgerEvent::MemberAdded {
+                project_id,
+                account,
+            });
+        }
+        Ok(())
+    }
+
+    fn remove_key(&mut self, project_id: ProjectId, account: AccountId) -> Result<(), LedgerError> {
+        let mut project = self.get_project_as_member(project_id)?;
+        if project.members.contains(&account) {
+            if project.members.len() == 1 {
+                return Err(KeysetError::LastMemberRemoved.into());
+            }
+            let mut account_projects = self.member_projects(&account)?;
+            account_projects.retain(|id| *id != project_id);
+            project.members.retain(|member| *member != account);
+
+            let storage = &mut self.storage();
+            projects().insert(storage, &project_id, &project);
+            member_projects().insert(storage, &account, &account_projects);
+            self.emit_event(&LedgerEvent::MemberRemoved {
+                project_id,
+                account,
+            });
+        }
+        Ok(())
+    }
+
+    fn checkpoint(
+        &mut self,
+        project_id: ProjectId,
+        project_hash: Hash,
+        contributions: Vec<Contribution>,
+        dependency_updates: Vec<DependencyUpdate>,
+    ) -> Result<(), LedgerError> {
+        self.get_project_as_member(project_id)?;
+
+        let head = contributions_heads()
+            .get(&mut self.storage(), &project_id)?
+            .unwrap_or_default();
+        let new_head = follow_contributions(head, &contributions)
+            .ok_or(CheckpointError::ContributionsNotHashLinked)?;
+
+        for update in dependency_updates.iter() {
+            if let DependencyUpdate::Depend {
+                project_id: dependency_id,
+            } = update
+            {
+                if *dependency_id == project_id {
+                    return Err(CheckpointError::SelfDependency.into());
+                }
+                if !projects().contains(&mut self.storage(), dependency_id) {
+                    return Err(CheckpointError::UnknownDependency.into());
+                }
+            }
+        }
+
+        let mut project_dependencies = self.get_dependencies(project_id)?;
+        apply_dependency_updates(&mut project_dependencies, &dependency_updates)?;
+
+        // Read everything before we write so that we don’t change the state if reading fails.
+        let mut updated_dependents = Vec::new();
+        for update in dependency_updates.iter() {
+            let mut dependency_dependents = self.get_dependents(*update.project_id())?;
+            match update {
+                DependencyUpdate::Depend { .. } => dependency_dependents.push(project_id),
+                DependencyUpdate::Undepend { .. } => {
+                    dependency_dependents.retain(|id| *id != project_id)
+                }
+            }
+            updated_dependents.push((*update.project_id(), dependency_dependents));
+        }
+
+        let checkpoint = Checkpoint {
+            project_hash,
+            contributions,
+            dependency_updates,
+        };
+        let storage = &mut self.storage();
+        let index = checkpoints(&project_id).push(storage, &checkpoint)?;
+        for (dependency_id, dependency_dependents) in updated_dependents {
+            dependents().insert(storage, &dependency_id, &dependency_dependents);
+        }
+        dependencies().insert(storage, &project_id, &project_dependencies);
+        contributions_heads().insert(storage, &project_id, &new_head);
+
+        for update in checkpoint.dependency_updates.iter() {
+            let dependency_id = *update.project_id();
+            self.emit_event(&match update {
+                DependencyUpdate::Depend { .. } => LedgerEvent::DependencyAdded {
+                    project_id,
+                    dependency_id,
+                },
+                DependencyUpdate::Undepend { .. } => LedgerEvent::DependencyRemoved {
+                    project_id,
+                    dependency_id,
+                },
+            });
+        }
+        self.emit_event(&LedgerEvent::CheckpointAdded { project_id, index });
+        Ok(())
+    }
+
+    fn get_checkpoint(
+        &mut self,
+        project_id: ProjectId,
+        index: u64,
+    ) -> Result<Option<Checkpoint>, LedgerError> {
+        Ok(checkpoints(&project_id).get(&mut self.storage(), index)?)
+    }
+
+    fn get_dependencies(&mut self, project_id: ProjectId) -> Result<Vec<ProjectId>, LedgerError> {
+        Ok(dependencies()
+            .get(&mut self.storage(), &project_id)?
+            .unwrap_or_default())
+    }
+
+    fn get_dependents(&mut self, project_id: ProjectId) -> Result<Vec<ProjectId>, LedgerError> {
+        Ok(dependents()
+            .get(&mut self.storage(), &project_id)?
+            .unwrap_or_default())
+    }
+
+    fn transfer(&mut self, to: AccountId, amount: Oscoin) -> Result<(), LedgerError> {
+        let from = self.env.sender().to_fixed_bytes();
+        let mut from_account = self.get_account(from)?;
+        from_account.balance = from_account
+            .balance
+            .checked_sub(amount)
+            .ok_or(TransferError::InsufficientBalance)?;
+        from_account.nonce += 1;
+
+        if from == to {
+            from_account.balance += amount;
+            accounts().insert(&mut self.storage(), &from, &from_account);
+        } else {
+            let mut to_account = self.get_account(to)?;
+            to_account.balance = to_account
+                .balance
+                .checked_add(amount)
+                .ok_or(TransferError::BalanceOverflow)?;
+            let storage = &mut self.storage();
+            accounts().insert(storage, &from, &from_account);
+            accounts().insert(storage, &to, &to_account);
+        }
+        self.emit_event(&LedgerEvent::Transferred { from, to, amount });
+        Ok(())
+    }
+
+    fn get_account(&mut self, address: AccountId) -> Result<Account, LedgerError> {
+        Ok(accounts()
+            .get(&mut self.storage(), &address)?
+            .unwrap_or_default())
+    }
+
+    fn pause(&mut self) -> Result<(), LedgerError> {
+        let (owner, _) = self.get_owners_as_owner()?;
+        if !self.is_paused()? {
+            paused().insert(&mut self.storage(), &true);
+            self.emit_event(&LedgerEvent::Paused { owner });
+        }
+        Ok(())
+    }
+
+    fn unpause(&mut self) -> Result<(), LedgerError> {
+        let (owner, _) = self.get_owners_as_owner()?;
+        if self.is_paused()? {
+            paused().remove(&mut self.storage());
+            self.emit_event(&LedgerEvent::Unpaused { owner });
+        }
+        Ok(())
+    }
+
+    fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), LedgerError> {
+        let (sender, mut owner_list) = self.get_owners_as_owner()?;
+        if new_owner == sender {
+            return Ok(());
+        }
+        owner_list.retain(|owner| *owner != sender);
+        if !owner_list.contains(&new_owner) {
+            owner_list.push(new_owner);
+        }
+        owners().insert(&mut self.storage(), &owner_list);
+        self.emit_event(&LedgerEvent::OwnershipTransferred {
+            from: sender,
+            to: new_owner,
+        });
+        Ok(())
+    }
+
+    fn is_paused(&mut self) -> Result<bool, LedgerError> {
+        Ok(paused().get(&mut self.storage())?.unwrap_or(false))
+    }
+
+    fn get_owners(&mut self) -> Result<Vec<AccountId>, LedgerError> {
+        Ok(owners().get(&mut self.storage())?.unwrap_or_default())
+    }
+
+    fn export_state(&mut self, cursor: u64) -> Result<StatePage, LedgerError> {
+        let page = self.list_projects(cursor, MAX_PAGE_SIZE, ProjectFilter::default())?;
+        Ok(StatePage {
+            counter: self.counter_value()?,
+            projects: page.projects,
+            next_cursor: page.next_offset,
+        })
+    }
+
+    fn upgrade(&mut self, new_implementation: AccountId) -> Result<(), LedgerError> {
+        self.get_owners_as_owner()?;
+        if !implementation().contains(&mut self.storage()) {
+            return Err(LedgerError::NotUpgradable);
+        }
+        implementation().insert(&mut self.storage(), &new_implementation);
+        self.emit_event(&LedgerEvent::Upgraded {
+            implementation: new_implementation,
+        });
+        Ok(())
+    }
+}
+
+/// Returns true if an item occurs more than once in `items`.
+fn has_duplicates<T: PartialEq>(items: &[T]) -> bool {
+    items
+        .iter()
+        .enumerate()
+        .any(|(i, item)| items[..i].contains(item))
+}
+
+/// Applies `updates` to `dependencies` from left to right.
+///
+/// Returns an error if an update adds a dependency that is already used, removes a dependency
+/// that is not used, or if there is more than one update for the same project. `dependencies`
+/// may be partially updated in that case.
+fn apply_dependency_updates(
+    dependencies: &mut Vec<ProjectId>,
+    updates: &[DependencyUpdate],
+) -> Result<(), CheckpointError> {
+    for (i, update) in updates.iter().enumerate() {
+        if updates[..i]
+            .iter()
+            .any(|previous| previous.project_id() == update.project_id())
+        {
+            return Err(CheckpointError::DuplicateDependencies);
+        }
+        match update {
+            DependencyUpdate::Depend { project_id } => {
+                if dependencies.contains(project_id) {
+                    return Err(CheckpointError::UsedDependencyAdded);
+                }
+                dependencies.push(*project_id);
+            }
+            DependencyUpdate::Undepend { project_id } => {
+                if !dependencies.contains(project_id) {
+                    return Err(CheckpointError::UnusedDependencyRemoved);
+                }
+                dependencies.retain(|id| id != project_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `contributions` form a hash-linked list that starts at `head` and returns the
+/// `commit` hash of the last contribution. Returns `None` if the list is not hash-linked.
+///
+/// If `contributions` is empty `head` is returned.
+fn follow_contributions(mut head: Hash, contributions: &[Contribution]) -> Option<Hash> {
+    for contribution in contributions {
+        if contribution.prev != head {
+            return None;
+        }
+        head = contribution.commit;
+    }
+    Some(head)
+}
+
+/// Computes the ID of a project through a cryptographic hash of its creator and the number of
+/// projects the creator has registered before.
 ///
-/// *FIXME* Two project registration transactions from the same author in the same block will
-/// result in the same project ID. In this case the first project is overwritten. We could prevent
-/// this by including the account nonce in the computation but this is unavialble from the Pwasm
-/// environment.
-pub fn compute_project_id(creator: Address, block_number: u64) -> ProjectId {
-    let mut data = Vec::from(creator.as_bytes());
-    data.extend_from_slice(&block_number.to_be_bytes());
+/// The ledger keeps its own registration count for every account because the transaction nonce is
+/// not available in the Pwasm environment. Since the count is incremented with every
+/// registration the IDs of projects registered by the same creator never collide, even if they are
+/// registered in the same block.
+pub fn compute_project_id(creator: AccountId, registration_count: u64) -> ProjectId {
+    let mut data = Vec::from(&creator[..]);
+    data.extend_from_slice(&registration_count.to_be_bytes());
     let hash = pwasm_std::keccak(&data);
     let mut project_id: [u8; 20] = Default::default();
     project_id.copy_from_slice(&hash[0..20]);
@@ -122,16 +918,16 @@ mod test {
     fn counter_inc() {
         let mut ledger = new_ledger();
         for _ in 0..10 {
-            ledger.counter_inc()
+            ledger.counter_inc().unwrap()
         }
-        let counter = ledger.counter_value();
+        let counter = ledger.counter_value().unwrap();
         assert_eq!(counter, 10);
     }
 
     #[test]
     fn counter_default() {
         let mut ledger = new_ledger();
-        let counter = ledger.counter_value();
+        let counter = ledger.counter_value().unwrap();
         assert_eq!(counter, 0);
     }
 
@@ -142,9 +938,10 @@ mod test {
         let name = "monokol";
         let description = "Looking glass into the future.";
         let img_url = "https://monok.el/img/logo.svg";
-        let project_id =
-            ledger.register_project(name.to_owned(), description.to_owned(), img_url.to_owned());
-        let project = ledger.get_project(project_id).unwrap();
+        let project_id = ledger
+            .register_project(name.to_owned(), description.to_owned(), img_url.to_owned())
+            .unwrap();
+        let project = ledger.get_project(project_id).unwrap().unwrap();
 
         assert_eq!(project.name, name);
         assert_eq!(project.description, description);
@@ -152,7 +949,1299 @@ mod test {
         assert_eq!(project.members, vec![test_sender().to_fixed_bytes()]);
     }
 
-    fn new_ledger() -> Ledger_ {
+    #[test]
+    fn register_project_same_block() {
+        let mut ledger = new_ledger();
+        let first = register_test_project(&mut ledger);
+        let second = register_test_project(&mut ledger);
+
+        assert_ne!(first, second);
+        assert_eq!(first, compute_project_id(test_sender().to_fixed_bytes(), 0));
+        assert_eq!(
+            second,
+            compute_project_id(test_sender().to_fixed_bytes(), 1)
+        );
+        assert_eq!(list_all_projects(&mut ledger).len(), 2);
+    }
+
+    #[test]
+    fn invalid_storage() {
+        let mut ledger = new_ledger();
+        StorageValue::<String>::new(b"counter")
+            .with_encoding(Encoding::Compact)
+            .insert(&mut ledger.storage(), &"not a number".to_owned());
+        assert_eq!(ledger.counter_value(), Err(LedgerError::InvalidStorage));
+    }
+
+    #[test]
+    fn unregister_project() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        let other_project_id = register_test_project(&mut ledger);
+
+        ledger.unregister_project(project_id).unwrap();
+
+        assert_eq!(ledger.get_project(project_id).unwrap(), None);
+        let project_ids = list_all_projects(&mut ledger)
+            .iter()
+            .map(|project| project.id)
+            .collect::<Vec<_>>();
+        assert_eq!(project_ids, vec![other_project_id]);
+        let member_page = ledger
+            .list_projects(
+                0,
+                MAX_PAGE_SIZE,
+                ProjectFilter {
+                    member: Some(test_sender().to_fixed_bytes()),
+                    name_prefix: None,
+                },
+            )
+            .unwrap();
+        assert_eq!(member_page.projects.len(), 1);
+        assert_eq!(member_page.projects[0].id, other_project_id);
+    }
+
+    #[test]
+    fn unregister_last_project() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        let other_project_id = register_test_project(&mut ledger);
+
+        ledger.unregister_project(other_project_id).unwrap();
+        ledger.unregister_project(project_id).unwrap();
+
+        assert_eq!(list_all_projects(&mut ledger), vec![]);
+        assert_eq!(ledger.project_count().unwrap(), 0);
+    }
+
+    #[test]
+    fn list_projects_pages() {
+        let mut ledger = new_ledger();
+        let project_ids = (0..5)
+            .map(|_| register_test_project(&mut ledger))
+            .collect::<Vec<_>>();
+
+        let first = ledger
+            .list_projects(0, 2, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(first.next_offset, Some(2));
+        let second = ledger
+            .list_projects(2, 2, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(second.next_offset, Some(4));
+        let third = ledger
+            .list_projects(4, 2, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(third.next_offset, None);
+
+        let listed_ids = first
+            .projects
+            .iter()
+            .chain(second.projects.iter())
+            .chain(third.projects.iter())
+            .map(|project| project.id)
+            .collect::<Vec<_>>();
+        assert_eq!(listed_ids, project_ids);
+
+        let past_end = ledger
+            .list_projects(10, 2, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(past_end, ProjectPage::default());
+    }
+
+    #[test]
+    fn pages_fit_into_proxy_response() {
+        let mut ledger = new_ledger();
+        // About eight projects of this size fit into one response.
+        let description = "x".repeat(8 * 1024);
+        let project_ids = (0..20)
+            .map(|_| {
+                ledger
+                    .register_project("monokol".to_owned(), description.clone(), String::new())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let mut listed_ids = Vec::new();
+        let mut offset = Some(0);
+        while let Some(current) = offset {
+            let call = Call::Query(Query::ListProjects {
+                offset: current,
+                limit: MAX_PAGE_SIZE,
+                filter: ProjectFilter::default(),
+            });
+            let response = ledger.call(&call.serialize());
+            assert!(response.len() <= proxy::MAX_RESPONSE_SIZE);
+            let page = serde_cbor::from_slice::<Result<ProjectPage, LedgerError>>(&response)
+                .unwrap()
+                .unwrap();
+            if page.next_offset.is_some() {
+                // The page is only cut short when the next project does not fit.
+                assert!(response.len() + description.len() > proxy::MAX_RESPONSE_SIZE);
+            }
+            listed_ids.extend(page.projects.iter().map(|project| project.id));
+            offset = page.next_offset;
+        }
+        assert_eq!(listed_ids, project_ids);
+
+        let response = ledger.call(&Call::Query(Query::ExportState { cursor: 0 }).serialize());
+        assert!(response.len() <= proxy::MAX_RESPONSE_SIZE);
+        let page = serde_cbor::from_slice::<Result<StatePage, LedgerError>>(&response)
+            .unwrap()
+            .unwrap();
+        assert_eq!(page.next_cursor, Some(page.projects.len() as u64));
+    }
+
+    #[test]
+    fn list_projects_zero_limit() {
+        let mut ledger = new_ledger();
+        register_test_project(&mut ledger);
+
+        assert_eq!(
+            ledger.list_projects(0, 0, ProjectFilter::default()),
+            Err(LedgerError::InvalidCall)
+        );
+    }
+
+    #[test]
+    fn list_projects_filters() {
+        let mut ledger = new_ledger();
+        let member = Address::from_low_u64_le(1).to_fixed_bytes();
+        let monokol = register_test_project(&mut ledger);
+        let other = ledger
+            .register_project(
+                "other".to_owned(),
+                "Another project".to_owned(),
+                "https://example.com/logo.svg".to_owned(),
+            )
+            .unwrap();
+        ledger.add_key(other, member).unwrap();
+
+        let list_ids = |ledger: &mut Ledger_<pwasm::TestEnv>, filter| {
+            ledger
+                .list_projects(0, MAX_PAGE_SIZE, filter)
+                .unwrap()
+                .projects
+                .iter()
+                .map(|project| project.id)
+                .collect::<Vec<_>>()
+        };
+
+        let by_member = ProjectFilter {
+            member: Some(member),
+            name_prefix: None,
+        };
+        assert_eq!(list_ids(&mut ledger, by_member), vec![other]);
+
+        let by_name = ProjectFilter {
+            member: None,
+            name_prefix: Some("mono".to_owned()),
+        };
+        assert_eq!(list_ids(&mut ledger, by_name), vec![monokol]);
+
+        let by_both = ProjectFilter {
+            member: Some(member),
+            name_prefix: Some("mono".to_owned()),
+        };
+        assert_eq!(list_ids(&mut ledger, by_both), Vec::<ProjectId>::new());
+
+        ledger.remove_key(other, member).unwrap();
+        let by_member = ProjectFilter {
+            member: Some(member),
+            name_prefix: None,
+        };
+        assert_eq!(list_ids(&mut ledger, by_member), Vec::<ProjectId>::new());
+    }
+
+    #[test]
+    fn unregister_project_not_a_member() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+
+        ledger.env_mut().sender = Address::from_low_u64_le(1);
+        assert_eq!(
+            ledger.unregister_project(project_id),
+            Err(LedgerError::NotAProjectMember)
+        );
+    }
+
+    #[test]
+    fn unregister_project_removes_checkpoints_and_dependencies() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let dependency = register_test_project(&mut ledger);
+        ledger
+            .checkpoint(
+                project,
+                [1; 32],
+                contribution_list([0; 32], 2),
+                vec![DependencyUpdate::Depend {
+                    project_id: dependency,
+                }],
+            )
+            .unwrap();
+
+        ledger.unregister_project(project).unwrap();
+
+        assert_eq!(ledger.get_checkpoint(project, 0).unwrap(), None);
+        assert_eq!(
+            ledger.get_dependencies(project).unwrap(),
+            Vec::<ProjectId>::new()
+        );
+        assert_eq!(
+            ledger.get_dependents(dependency).unwrap(),
+            Vec::<ProjectId>::new()
+        );
+        let storage = &mut ledger.storage();
+        assert_eq!(checkpoints(&project).len(storage).unwrap(), 0);
+        assert!(!contributions_heads().contains(storage, &project));
+        assert!(!super::dependencies().contains(storage, &project));
+    }
+
+    #[test]
+    fn unregister_project_with_dependents() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let dependent = register_test_project(&mut ledger);
+        ledger
+            .checkpoint(
+                dependent,
+                [1; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Depend {
+                    project_id: project,
+                }],
+            )
+            .unwrap();
+
+        assert_eq!(
+            ledger.unregister_project(project),
+            Err(LedgerError::ProjectHasDependents)
+        );
+        assert!(ledger.get_project(project).unwrap().is_some());
+
+        ledger
+            .checkpoint(
+                dependent,
+                [2; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Undepend {
+                    project_id: project,
+                }],
+            )
+            .unwrap();
+        ledger.unregister_project(project).unwrap();
+        assert_eq!(ledger.get_project(project).unwrap(), None);
+    }
+
+    #[test]
+    fn too_large_response() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let dependent = register_test_project(&mut ledger);
+        ledger
+            .checkpoint(
+                dependent,
+                [1; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Depend {
+                    project_id: project,
+                }],
+            )
+            .unwrap();
+        // Checkpointing enough dependents to exceed the response size takes too long so we grow
+        // the list in storage. The IDs of this many dependents do not fit into one response.
+        let mut project_dependents = ledger.get_dependents(project).unwrap();
+        project_dependents.extend((0..2000u16).map(|i| {
+            let mut id = [0xff; 20];
+            id[..2].copy_from_slice(&i.to_le_bytes());
+            id
+        }));
+        super::dependents().insert(&mut ledger.storage(), &project, &project_dependents);
+        assert_eq!(ledger.get_dependents(project).unwrap().len(), 2001);
+
+        let call = Call::Query(Query::GetDependents {
+            project_id: project,
+        });
+        let response = ledger.call(&call.serialize());
+        assert_eq!(
+            serde_cbor::from_slice::<Result<Vec<ProjectId>, LedgerError>>(&response).unwrap(),
+            Err(LedgerError::ResponseTooLarge)
+        );
+    }
+
+    #[test]
+    fn add_key() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        let new_member = Address::from_low_u64_le(1).to_fixed_bytes();
+
+        ledger.add_key(project_id, new_member).unwrap();
+        // Adding a member twice has no effect
+        ledger.add_key(project_id, new_member).unwrap();
+
+        let members = vec![test_sender().to_fixed_bytes(), new_member];
+        assert_eq!(
+            ledger.get_project(project_id).unwrap().unwrap().members,
+            members
+        );
+        assert_eq!(list_all_projects(&mut ledger)[0].members, members);
+    }
+
+    #[test]
+    fn remove_key() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        let new_member = Address::from_low_u64_le(1);
+        ledger
+            .add_key(project_id, new_member.to_fixed_bytes())
+            .unwrap();
+
+        // Members can remove themselves
+        ledger.env_mut().sender = new_member;
+        ledger
+            .remove_key(project_id, new_member.to_fixed_bytes())
+            .unwrap();
+
+        let members = vec![test_sender().to_fixed_bytes()];
+        assert_eq!(
+            ledger.get_project(project_id).unwrap().unwrap().members,
+            members
+        );
+        assert_eq!(list_all_projects(&mut ledger)[0].members, members);
+    }
+
+    #[test]
+    fn add_key_errors() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        let other = Address::from_low_u64_le(1);
+
+        assert_eq!(
+            ledger.add_key([0; 20], other.to_fixed_bytes()),
+            Err(LedgerError::ProjectNotFound)
+        );
+
+        ledger.env_mut().sender = other;
+        assert_eq!(
+            ledger.add_key(project_id, other.to_fixed_bytes()),
+            Err(LedgerError::NotAProjectMember)
+        );
+    }
+
+    #[test]
+    fn remove_last_key() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+        assert_eq!(
+            ledger.remove_key(project_id, test_sender().to_fixed_bytes()),
+            Err(KeysetError::LastMemberRemoved.into())
+        );
+    }
+
+    #[test]
+    fn checkpoint() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+
+        let first = contribution_list([0; 32], 3);
+        let second = contribution_list(first.last().unwrap().commit, 2);
+        ledger
+            .checkpoint(project_id, [1; 32], first.clone(), Vec::new())
+            .unwrap();
+        ledger
+            .checkpoint(project_id, [2; 32], Vec::new(), Vec::new())
+            .unwrap();
+        ledger
+            .checkpoint(project_id, [3; 32], second.clone(), Vec::new())
+            .unwrap();
+
+        let checkpoints = (0..3)
+            .map(|index| ledger.get_checkpoint(project_id, index).unwrap().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            checkpoints,
+            vec![
+                Checkpoint {
+                    project_hash: [1; 32],
+                    contributions: first,
+                    dependency_updates: Vec::new(),
+                },
+                Checkpoint {
+                    project_hash: [2; 32],
+                    contributions: Vec::new(),
+                    dependency_updates: Vec::new(),
+                },
+                Checkpoint {
+                    project_hash: [3; 32],
+                    contributions: second,
+                    dependency_updates: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(ledger.get_checkpoint(project_id, 3).unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoint_broken_link() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+
+        let mut contributions = contribution_list([0; 32], 3);
+        contributions[1].prev = [0xff; 32];
+        assert_eq!(
+            ledger.checkpoint(project_id, [1; 32], contributions, Vec::new()),
+            Err(CheckpointError::ContributionsNotHashLinked.into())
+        );
+        assert_eq!(ledger.get_checkpoint(project_id, 0).unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoint_not_linked_to_previous() {
+        let mut ledger = new_ledger();
+        let project_id = register_test_project(&mut ledger);
+
+        let contributions = contribution_list([0; 32], 3);
+        ledger
+            .checkpoint(project_id, [1; 32], contributions.clone(), Vec::new())
+            .unwrap();
+        assert_eq!(
+            ledger.checkpoint(project_id, [2; 32], contributions, Vec::new()),
+            Err(CheckpointError::ContributionsNotHashLinked.into())
+        );
+    }
+
+    #[test]
+    fn dependencies() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let dep1 = register_test_project(&mut ledger);
+        let dep2 = register_test_project(&mut ledger);
+
+        ledger
+            .checkpoint(
+                project,
+                [1; 32],
+                Vec::new(),
+                vec![
+                    DependencyUpdate::Depend { project_id: dep1 },
+                    DependencyUpdate::Depend { project_id: dep2 },
+                ],
+            )
+            .unwrap();
+        assert_eq!(ledger.get_dependencies(project).unwrap(), vec![dep1, dep2]);
+        assert_eq!(ledger.get_dependents(dep1).unwrap(), vec![project]);
+        assert_eq!(ledger.get_dependents(dep2).unwrap(), vec![project]);
+
+        ledger
+            .checkpoint(
+                project,
+                [2; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Undepend { project_id: dep1 }],
+            )
+            .unwrap();
+        assert_eq!(ledger.get_dependencies(project).unwrap(), vec![dep2]);
+        assert_eq!(
+            ledger.get_dependents(dep1).unwrap(),
+            Vec::<ProjectId>::new()
+        );
+        assert_eq!(ledger.get_dependents(dep2).unwrap(), vec![project]);
+    }
+
+    #[test]
+    fn apply_dependency_updates_errors() {
+        let dep1 = [1; 20];
+        let dep2 = [2; 20];
+        let depend = |project_id| DependencyUpdate::Depend { project_id };
+        let undepend = |project_id| DependencyUpdate::Undepend { project_id };
+
+        assert_eq!(
+            apply_dependency_updates(&mut vec![dep1], &[depend(dep1)]),
+            Err(CheckpointError::UsedDependencyAdded)
+        );
+        assert_eq!(
+            apply_dependency_updates(&mut vec![dep1], &[undepend(dep2)]),
+            Err(CheckpointError::UnusedDependencyRemoved)
+        );
+        assert_eq!(
+            apply_dependency_updates(&mut vec![dep1], &[undepend(dep1), depend(dep1)]),
+            Err(CheckpointError::DuplicateDependencies)
+        );
+        assert_eq!(
+            apply_dependency_updates(&mut vec![dep1], &[depend(dep2), undepend(dep1)]),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn checkpoint_invalid_dependency_update() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let dep = DependencyUpdate::Depend {
+            project_id: register_test_project(&mut ledger),
+        };
+
+        ledger
+            .checkpoint(project, [1; 32], Vec::new(), vec![dep.clone()])
+            .unwrap();
+        assert_eq!(
+            ledger.checkpoint(project, [2; 32], Vec::new(), vec![dep]),
+            Err(CheckpointError::UsedDependencyAdded.into())
+        );
+        assert_eq!(ledger.get_checkpoint(project, 1).unwrap(), None);
+    }
+
+    #[test]
+    fn checkpoint_self_dependency() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+
+        assert_eq!(
+            ledger.checkpoint(
+                project,
+                [1; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Depend {
+                    project_id: project
+                }],
+            ),
+            Err(CheckpointError::SelfDependency.into())
+        );
+        assert_eq!(ledger.get_checkpoint(project, 0).unwrap(), None);
+        assert_eq!(
+            ledger.get_dependents(project).unwrap(),
+            Vec::<ProjectId>::new()
+        );
+    }
+
+    #[test]
+    fn checkpoint_unknown_dependency() {
+        let mut ledger = new_ledger();
+        let project = register_test_project(&mut ledger);
+        let unknown = [1; 20];
+
+        assert_eq!(
+            ledger.checkpoint(
+                project,
+                [1; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Depend {
+                    project_id: unknown
+                }],
+            ),
+            Err(CheckpointError::UnknownDependency.into())
+        );
+        assert_eq!(ledger.get_checkpoint(project, 0).unwrap(), None);
+        assert_eq!(
+            ledger.get_dependents(unknown).unwrap(),
+            Vec::<ProjectId>::new()
+        );
+    }
+
+    #[test]
+    fn transfer() {
+        let mut ledger = new_ledger();
+        let sender = test_sender().to_fixed_bytes();
+        let receiver = Address::from_low_u64_le(1).to_fixed_bytes();
+        ledger.mint(sender, 100).unwrap();
+
+        ledger.transfer(receiver, 30).unwrap();
+        ledger.transfer(receiver, 20).unwrap();
+
+        assert_eq!(
+            ledger.get_account(sender).unwrap(),
+            Account {
+                nonce: 2,
+                balance: 50
+            }
+        );
+        assert_eq!(
+            ledger.get_account(receiver).unwrap(),
+            Account {
+                nonce: 0,
+                balance: 50
+            }
+        );
+    }
+
+    #[test]
+    fn transfer_to_self() {
+        let mut ledger = new_ledger();
+        let sender = test_sender().to_fixed_bytes();
+        ledger.mint(sender, 100).unwrap();
+
+        ledger.transfer(sender, 100).unwrap();
+
+        assert_eq!(
+            ledger.get_account(sender).unwrap(),
+            Account {
+                nonce: 1,
+                balance: 100
+            }
+        );
+    }
+
+    #[test]
+    fn transfer_insufficient_balance() {
+        let mut ledger = new_ledger();
+        let sender = test_sender().to_fixed_bytes();
+        ledger.mint(sender, 10).unwrap();
+        assert_eq!(
+            ledger.transfer(Address::from_low_u64_le(1).to_fixed_bytes(), 11),
+            Err(TransferError::InsufficientBalance.into())
+        );
+        assert_eq!(ledger.get_account(sender).unwrap().balance, 10);
+    }
+
+    #[test]
+    fn transfer_balance_overflow() {
+        let mut ledger = new_ledger();
+        let sender = test_sender().to_fixed_bytes();
+        let receiver = Address::from_low_u64_le(1).to_fixed_bytes();
+        ledger.mint(sender, 10).unwrap();
+        ledger.mint(receiver, Oscoin::max_value()).unwrap();
+        assert_eq!(
+            ledger.mint(receiver, 1),
+            Err(TransferError::BalanceOverflow.into())
+        );
+        assert_eq!(
+            ledger.transfer(receiver, 10),
+            Err(TransferError::BalanceOverflow.into())
+        );
+        assert_eq!(ledger.get_account(sender).unwrap().balance, 10);
+        assert_eq!(
+            ledger.get_account(receiver).unwrap().balance,
+            Oscoin::max_value()
+        );
+    }
+
+    #[test]
+    fn events() {
+        let mut ledger = new_ledger();
+        let sender = test_sender().to_fixed_bytes();
+        let other = Address::from_low_u64_le(1).to_fixed_bytes();
+
+        let project_id = register_test_project(&mut ledger);
+        let dependency_id = register_test_project(&mut ledger);
+        ledger.add_key(project_id, other).unwrap();
+        ledger.remove_key(project_id, other).unwrap();
+        ledger
+            .checkpoint(
+                project_id,
+                [1; 32],
+                Vec::new(),
+                vec![DependencyUpdate::Depend {
+                    project_id: dependency_id,
+                }],
+            )
+            .unwrap();
+        ledger.mint(sender, 10).unwrap();
+        ledger.transfer(other, 10).unwrap();
+        ledger.unregister_project(project_id).unwrap();
+
+        assert_eq!(
+            emitted_events(&mut ledger),
+            vec![
+                LedgerEvent::ProjectRegistered {
+                    project_id,
+                    creator: sender
+                },
+                LedgerEvent::ProjectRegistered {
+                    project_id: dependency_id,
+                    creator: sender
+                },
+                LedgerEvent::MemberAdded {
+                    project_id,
+                    account: other
+                },
+                LedgerEvent::MemberRemoved {
+                    project_id,
+                    account: other
+                },
+                LedgerEvent::DependencyAdded {
+                    project_id,
+                    dependency_id
+                },
+                LedgerEvent::CheckpointAdded {
+                    project_id,
+                    index: 0
+                },
+                LedgerEvent::Minted {
+                    to: sender,
+                    amount: 10
+                },
+                LedgerEvent::Transferred {
+                    from: sender,
+                    to: other,
+                    amount: 10
+                },
+                LedgerEvent::ProjectUnregistered { project_id },
+            ]
+        );
+    }
+
+    #[test]
+    fn no_events_on_error() {
+        let mut ledger = new_ledger();
+        assert!(ledger.transfer([1; 20], 1).is_err());
+        assert!(ledger.add_key([1; 20], [1; 20]).is_err());
+        assert_eq!(emitted_events(&mut ledger), vec![]);
+    }
+
+    #[test]
+    fn dispatch_encodes_errors() {
+        let mut ledger = new_ledger();
+        let call = Call::Update(Update::AddKey {
+            project_id: [0; 20],
+            account: [0; 20],
+        });
+        let response = dispatch(&mut ledger, call);
+        let result: Result<(), LedgerError> = serde_cbor::from_slice(&response).unwrap();
+        assert_eq!(result, Err(LedgerError::ProjectNotFound));
+    }
+
+    #[test]
+    fn call_logs_update_result() {
+        let mut ledger = new_ledger();
+        let call = Call::Update(Update::RegisterProject {
+            name: "monokol".to_owned(),
+            description: "Looking glass into the future.".to_owned(),
+            img_url: "https://monok.el/img/logo.svg".to_owned(),
+        });
+        let response = ledger.call(&call.serialize());
+
+        let project_id = compute_project_id(test_sender().to_fixed_bytes(), 0);
+        assert_eq!(response, encode_result(Ok(project_id)));
+        // The result is logged after the events emitted by the update.
+        assert_eq!(
+            ledger.env_mut().logs.last(),
+            Some(&pwasm::Log {
+                topics: vec![pwasm::H256::from(UPDATE_RESULT_TOPIC)],
+                data: response,
+            })
+        );
+    }
+
+    #[test]
+    fn call_does_not_log_query_result() {
+        let mut ledger = new_ledger();
+        let response = ledger.call(&Call::Query(Query::Ping).serialize());
+        assert_eq!(response, encode_result(Ok("pong")));
+        assert_eq!(ledger.env_mut().logs, vec![]);
+    }
+
+    #[test]
+    fn call_invalid_input() {
+        let mut ledger = new_ledger();
+        let response = ledger.call(b"not cbor");
+        assert_eq!(response, encode_result::<()>(Err(LedgerError::InvalidCall)));
+    }
+
+    #[test]
+    fn call_logs_error_results() {
+        let mut ledger = new_ledger();
+        let result_log = |data: Vec<u8>| pwasm::Log {
+            topics: vec![pwasm::H256::from(UPDATE_RESULT_TOPIC)],
+            data,
+        };
+
+        let invalid_call_response = ledger.call(b"not cbor");
+
+        storage_version().insert(&mut ledger.storage(), &(STORAGE_VERSION + 1));
+        let update = Call::Update(Update::CounterInc);
+        let migrate_response = ledger.call(&update.serialize());
+        assert_eq!(
+            migrate_response,
+            encode_result::<()>(Err(LedgerError::InvalidStorage))
+        );
+        // Failed queries are not logged either.
+        ledger.call(&Call::Query(Query::Ping).serialize());
+
+        assert_eq!(
+            ledger.env_mut().logs,
+            vec![
+                result_log(invalid_call_response),
+                result_log(migrate_response)
+            ]
+        );
+    }
+
+    #[test]
+    fn deploy_genesis() {
+        let mut ledger = new_ledger();
+        let owner = [1; 20];
+        let other = [2; 20];
+        let genesis = GenesisConfig {
+            owners: vec![owner],
+            projects: vec![GenesisProject {
+                name: "monokol".to_owned(),
+                description: "Looking glass into the future.".to_owned(),
+                img_url: "https://monok.el/img/logo.svg".to_owned(),
+                members: vec![owner, other],
+                id: None,
+            }],
+            balances: vec![(other, 100)],
+            params: ProtocolParams { max_page_size: 1 },
+            counter: 0,
+        };
+
+        ledger.deploy(&genesis.serialize()).unwrap();
+
+        let project_id = compute_project_id(owner, 0);
+        let project = ledger.get_project(project_id).unwrap().unwrap();
+        assert_eq!(project.members, vec![owner, other]);
+        assert_eq!(ledger.member_projects(&other).unwrap(), vec![project_id]);
+        assert_eq!(ledger.get_account(other).unwrap().balance, 100);
+        assert_eq!(
+            owners().get(&mut ledger.storage()).unwrap(),
+            Some(vec![owner])
+        );
+
+        ledger.env_mut().sender = Address::from(owner);
+        register_test_project(&mut ledger);
+        let page = ledger
+            .list_projects(0, MAX_PAGE_SIZE, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(page.projects.len(), 1);
+        assert_eq!(page.next_offset, Some(1));
+    }
+
+    #[test]
+    fn deploy_empty_input() {
+        let mut ledger = new_ledger();
+        ledger.deploy(&[]).unwrap();
+        assert_eq!(ledger.protocol_params().unwrap(), ProtocolParams::default());
+        assert_eq!(
+            ledger.get_owners().unwrap(),
+            vec![test_sender().to_fixed_bytes()]
+        );
+        assert_eq!(list_all_projects(&mut ledger), vec![]);
+    }
+
+    #[test]
+    fn deploy_invalid_genesis() {
+        let mut ledger = new_ledger();
+        assert_eq!(ledger.deploy(b"foo"), Err(LedgerError::InvalidGenesis));
+
+        let genesis = GenesisConfig {
+            projects: vec![GenesisProject {
+                name: "monokol".to_owned(),
+                description: "Looking glass into the future.".to_owned(),
+                img_url: "https://monok.el/img/logo.svg".to_owned(),
+                members: vec![],
+                id: None,
+            }],
+            ..GenesisConfig::default()
+        };
+        assert_eq!(
+            ledger.deploy(&genesis.serialize()),
+            Err(LedgerError::InvalidGenesis)
+        );
+    }
+
+    #[test]
+    fn deploy_genesis_duplicates() {
+        let account = [1; 20];
+        let project = GenesisProject {
+            name: "monokol".to_owned(),
+            description: "Looking glass into the future.".to_owned(),
+            img_url: "https://monok.el/img/logo.svg".to_owned(),
+            members: vec![account, account],
+            id: None,
+        };
+        let invalid_genesis = vec![
+            GenesisConfig {
+                owners: vec![account, account],
+                ..GenesisConfig::default()
+            },
+            GenesisConfig {
+                balances: vec![(account, Oscoin::max_value()), (account, 1)],
+                ..GenesisConfig::default()
+            },
+            GenesisConfig {
+                balances: vec![(account, 1), (account, 1)],
+                ..GenesisConfig::default()
+            },
+            GenesisConfig {
+                projects: vec![project],
+                ..GenesisConfig::default()
+            },
+        ];
+        for genesis in invalid_genesis {
+            let mut ledger = new_ledger();
+            assert_eq!(
+                ledger.deploy(&genesis.serialize()),
+                Err(LedgerError::InvalidGenesis)
+            );
+            assert_eq!(ledger.get_account(account).unwrap().balance, 0);
+        }
+    }
+
+    #[test]
+    fn pause() {
+        let mut ledger = new_ledger();
+        ledger.deploy(&[]).unwrap();
+        let counter_inc = Call::Update(Update::CounterInc).serialize();
+
+        let response = ledger.call(&Call::Update(Update::Pause).serialize());
+        assert_eq!(response, encode_result(Ok(())));
+        assert_eq!(ledger.is_paused(), Ok(true));
+        let response = ledger.call(&counter_inc);
+        assert_eq!(response, encode_result::<()>(Err(LedgerError::Paused)));
+        let response = ledger.call(&Call::Query(Query::CounterValue).serialize());
+        assert_eq!(response, encode_result(Ok(0u32)));
+
+        let response = ledger.call(&Call::Update(Update::Unpause).serialize());
+        assert_eq!(response, encode_result(Ok(())));
+        let response = ledger.call(&counter_inc);
+        assert_eq!(response, encode_result(Ok(())));
+
+        let owner = test_sender().to_fixed_bytes();
+        assert_eq!(
+            emitted_events(&mut ledger),
+            vec![
+                LedgerEvent::Paused { owner },
+                LedgerEvent::Unpaused { owner },
+                LedgerEvent::CounterIncremented { value: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn pause_not_an_owner() {
+        let mut ledger = new_ledger();
+        ledger.deploy(&[]).unwrap();
+        ledger.env_mut().sender = Address::from_low_u64_le(1);
+
+        assert_eq!(ledger.pause(), Err(LedgerError::NotAnOwner));
+        assert_eq!(ledger.unpause(), Err(LedgerError::NotAnOwner));
+        assert_eq!(
+            ledger.transfer_ownership([1; 20]),
+            Err(LedgerError::NotAnOwner)
+        );
+        assert_eq!(ledger.is_paused(), Ok(false));
+    }
+
+    #[test]
+    fn transfer_ownership() {
+        let mut ledger = new_ledger();
+        ledger.deploy(&[]).unwrap();
+        let sender = test_sender().to_fixed_bytes();
+        let new_owner = Address::from_low_u64_le(1);
+        let transfer_ownership = Call::Update(Update::TransferOwnership {
+            new_owner: new_owner.to_fixed_bytes(),
+        })
+        .serialize();
+
+        ledger.pause().unwrap();
+        let response = ledger.call(&transfer_ownership);
+        assert_eq!(response, encode_result::<()>(Err(LedgerError::Paused)));
+        ledger.unpause().unwrap();
+
+        let response = ledger.call(&transfer_ownership);
+        assert_eq!(response, encode_result(Ok(())));
+        assert_eq!(
+            ledger.get_owners().unwrap(),
+            vec![new_owner.to_fixed_bytes()]
+        );
+        assert_eq!(ledger.pause(), Err(LedgerError::NotAnOwner));
+
+        ledger.env_mut().sender = new_owner;
+        ledger.pause().unwrap();
+        assert_eq!(ledger.is_paused(), Ok(true));
+        assert!(
+            emitted_events(&mut ledger).contains(&LedgerEvent::OwnershipTransferred {
+                from: sender,
+                to: new_owner.to_fixed_bytes(),
+            })
+        );
+    }
+
+    #[test]
+    fn migrate() {
+        let mut ledger = new_ledger();
+        let response = ledger.call(&Call::Query(Query::Ping).serialize());
+        assert_eq!(response, encode_result(Ok("pong")));
+        assert_eq!(
+            storage_version().get(&mut ledger.storage()).unwrap(),
+            Some(STORAGE_VERSION)
+        );
+
+        storage_version().insert(&mut ledger.storage(), &(STORAGE_VERSION + 1));
+        let response = ledger.call(&Call::Query(Query::Ping).serialize());
+        assert_eq!(
+            response,
+            encode_result::<()>(Err(LedgerError::InvalidStorage))
+        );
+    }
+
+    #[test]
+    fn upgrade() {
+        let mut ledger = new_ledger();
+        ledger.deploy(&[]).unwrap();
+        let new_implementation = [2; 20];
+        let upgrade = Call::Update(Update::Upgrade {
+            implementation: new_implementation,
+        })
+        .serialize();
+
+        let response = ledger.call(&upgrade);
+        assert_eq!(
+            response,
+            encode_result::<()>(Err(LedgerError::NotUpgradable))
+        );
+
+        implementation().insert(&mut ledger.storage(), &[1; 20]);
+        ledger.pause().unwrap();
+        let response = ledger.call(&upgrade);
+        assert_eq!(response, encode_result::<()>(Err(LedgerError::Paused)));
+        assert_eq!(
+            implementation().get(&mut ledger.storage()).unwrap(),
+            Some([1; 20])
+        );
+
+        ledger.unpause().unwrap();
+        let response = ledger.call(&upgrade);
+        assert_eq!(response, encode_result(Ok(())));
+        assert_eq!(
+            implementation().get(&mut ledger.storage()).unwrap(),
+            Some(new_implementation)
+        );
+        assert_eq!(
+            emitted_events(&mut ledger).last(),
+            Some(&LedgerEvent::Upgraded {
+                implementation: new_implementation
+            })
+        );
+
+        ledger.env_mut().sender = Address::from_low_u64_le(1);
+        assert_eq!(ledger.upgrade([3; 20]), Err(LedgerError::NotAnOwner));
+    }
+
+    #[test]
+    fn export_and_import_state() {
+        let mut ledger = new_ledger();
+        let other = Address::from_low_u64_le(1).to_fixed_bytes();
+        ledger.counter_inc().unwrap();
+        for _ in 0..(MAX_PAGE_SIZE + 1) {
+            register_test_project(&mut ledger);
+        }
+        let project_id = register_test_project(&mut ledger);
+        ledger.add_key(project_id, other).unwrap();
+
+        let mut snapshot = StateSnapshot::new();
+        let mut cursor = Some(0);
+        while let Some(current_cursor) = cursor {
+            let page = ledger.export_state(current_cursor).unwrap();
+            cursor = page.next_cursor;
+            snapshot.add_page(page);
+        }
+        assert_eq!(snapshot.counter, 1);
+        assert_eq!(snapshot.projects.len() as u64, MAX_PAGE_SIZE + 2);
+
+        let mut imported = new_ledger();
+        imported
+            .deploy(&snapshot.clone().into_genesis().unwrap().serialize())
+            .unwrap();
+        assert_eq!(imported.counter_value().unwrap(), 1);
+        let mut projects = snapshot.projects;
+        let mut imported_projects = list_all_projects(&mut imported);
+        imported_projects.extend(
+            imported
+                .list_projects(MAX_PAGE_SIZE, MAX_PAGE_SIZE, ProjectFilter::default())
+                .unwrap()
+                .projects,
+        );
+        projects.sort();
+        imported_projects.sort();
+        assert_eq!(imported_projects, projects);
+        assert_eq!(imported.member_projects(&other).unwrap(), vec![project_id]);
+
+        // The registration counts are not imported. New projects must not take the IDs of
+        // imported projects.
+        let new_project_id = register_test_project(&mut imported);
+        assert!(!projects.iter().any(|project| project.id == new_project_id));
+    }
+
+    #[test]
+    fn import_unsupported_snapshot() {
+        let snapshot = StateSnapshot {
+            version: SNAPSHOT_VERSION + 1,
+            ..StateSnapshot::new()
+        };
+        assert_eq!(snapshot.into_genesis(), None);
+    }
+
+    #[test]
+    fn run_tx_reverts_on_error() {
+        let mut ledger = new_ledger();
+        let snapshot = ledger.env_mut().snapshot();
+
+        let result = ledger.run_tx(test_sender(), 1, |ledger| {
+            register_test_project(ledger);
+            ledger.transfer([1; 20], 10)
+        });
+
+        assert_eq!(
+            result,
+            Err(TxError::Ledger(TransferError::InsufficientBalance.into()))
+        );
+        assert_eq!(ledger.env_mut().snapshot(), snapshot);
+        assert_eq!(list_all_projects(&mut ledger), vec![]);
+    }
+
+    #[test]
+    fn run_tx_reverts_on_panic() {
+        let mut ledger = new_ledger();
+        let snapshot = ledger.env_mut().snapshot();
+
+        let result = ledger.run_tx(test_sender(), 1, |ledger| -> Result<(), LedgerError> {
+            register_test_project(ledger);
+            panic!("Wasm trap")
+        });
+
+        assert_eq!(result, Err(TxError::Panicked));
+        assert_eq!(ledger.env_mut().snapshot(), snapshot);
+        assert_eq!(emitted_events(&mut ledger), vec![]);
+    }
+
+    #[test]
+    fn run_tx_keeps_changes_on_success() {
+        let mut ledger = new_ledger();
+        let project_id = ledger
+            .run_tx(test_sender(), 1, |ledger| {
+                ledger.register_project(
+                    "monokol".to_owned(),
+                    "Looking glass into the future.".to_owned(),
+                    "https://monok.el/img/logo.svg".to_owned(),
+                )
+            })
+            .unwrap();
+        assert!(ledger.get_project(project_id).unwrap().is_some());
+    }
+
+    /// A failed registration must not leave partial writes behind even without the rollback
+    /// provided by the node.
+    #[test]
+    fn register_project_is_atomic() {
+        let mut ledger = new_ledger();
+        register_test_project(&mut ledger);
+        // Corrupt the sender’s project list so that the next registration fails after some
+        // reads.
+        member_projects()
+            .key(&test_sender().to_fixed_bytes())
+            .remove(&mut ledger.storage());
+        StorageMap::<AccountId, String>::new(b"member_projects").insert(
+            &mut ledger.storage(),
+            &test_sender().to_fixed_bytes(),
+            &"not a list".to_owned(),
+        );
+        let snapshot = ledger.env_mut().snapshot();
+
+        let result = ledger.register_project(
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        );
+
+        assert_eq!(result, Err(LedgerError::InvalidStorage));
+        assert_eq!(ledger.env_mut().snapshot(), snapshot);
+    }
+
+    #[test]
+    fn register_project_gas() {
+        let mut ledger = new_metered_ledger();
+        register_test_project(&mut ledger);
+        let first = ledger.env_mut().reset_meter();
+        assert!(first.net_gas() <= 310_000, "{:?}", first);
+
+        // Registration cost does not depend on the number of projects registered by others.
+        for i in 0..20 {
+            ledger.env_mut().inner.sender = Address::from_low_u64_le(i);
+            register_test_project(&mut ledger);
+        }
+        ledger.env_mut().inner.sender = Address::from_low_u64_le(1000);
+        ledger.env_mut().reset_meter();
+        register_test_project(&mut ledger);
+        let later = ledger.env_mut().reset_meter();
+        assert!(later.net_gas() <= first.net_gas(), "{:?}", later);
+    }
+
+    #[test]
+    fn list_projects_gas() {
+        let mut ledger = new_metered_ledger();
+        for _ in 0..30 {
+            register_test_project(&mut ledger);
+        }
+        ledger.env_mut().reset_meter();
+
+        let page = ledger
+            .list_projects(0, 10, ProjectFilter::default())
+            .unwrap();
+        assert_eq!(page.projects.len(), 10);
+        let meter = ledger.env_mut().reset_meter();
+        assert_eq!(meter.writes_new + meter.writes_overwrite, 0);
+        assert!(meter.net_gas() <= 25_000, "{:?}", meter);
+    }
+
+    /// Creates a hash-linked list of `len` contributions where the first contribution links to
+    /// `prev`.
+    fn contribution_list(prev: Hash, len: u8) -> Vec<Contribution> {
+        let mut contributions: Vec<Contribution> = Vec::new();
+        for i in 0..len {
+            let prev = contributions.last().map_or(prev, |c| c.commit);
+            let mut commit = prev;
+            commit[0] = commit[0].wrapping_add(i + 1);
+            contributions.push(Contribution {
+                prev,
+                commit,
+                author: test_sender().to_fixed_bytes(),
+                signoff: None,
+            });
+        }
+        contributions
+    }
+
+    /// Decodes the [LedgerEvent]s from the logs recorded by the test environment.
+    fn emitted_events(ledger: &mut Ledger_<pwasm::TestEnv>) -> Vec<LedgerEvent> {
+        ledger
+            .env_mut()
+            .logs
+            .iter()
+            .filter(|log| log.topics.first() == Some(&pwasm::H256::from(LEDGER_EVENT_TOPIC)))
+            .map(|log| {
+                let event: LedgerEvent = serde_cbor::from_slice(&log.data).unwrap();
+                assert_eq!(log.topics, event.topics());
+                event
+            })
+            .collect()
+    }
+
+    fn list_all_projects(ledger: &mut Ledger_<pwasm::TestEnv>) -> Vec<Project> {
+        ledger
+            .list_projects(0, MAX_PAGE_SIZE, ProjectFilter::default())
+            .unwrap()
+            .projects
+    }
+
+    fn register_test_project<E: pwasm::Env>(ledger: &mut Ledger_<E>) -> ProjectId {
+        ledger
+            .register_project(
+                "monokol".to_owned(),
+                "Looking glass into the future.".to_owned(),
+                "https://monok.el/img/logo.svg".to_owned(),
+            )
+            .unwrap()
+    }
+
+    fn new_metered_ledger() -> Ledger_<pwasm::MeteredEnv<pwasm::TestEnv>> {
+        let mut test_env = pwasm::TestEnv::new();
+        test_env.sender = test_sender();
+        Ledger_::new(pwasm::MeteredEnv::new(test_env))
+    }
+
+    fn new_ledger() -> Ledger_<pwasm::TestEnv> {
         let mut test_env = pwasm::TestEnv::new();
         test_env.sender = test_sender();
         Ledger_::new(test_env)
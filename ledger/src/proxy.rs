@@ -0,0 +1,134 @@
+//! Entry points of the ledger proxy contract.
+//!
+//! Deploying a new version of the ledger contract results in a new address and an empty state.
+//! To upgrade the ledger while keeping its address and state the ledger is deployed behind a
+//! proxy contract. The proxy holds the ledger state and forwards every call to the ledger
+//! implementation contract with a delegate call so that the implementation runs on the storage
+//! of the proxy.
+//!
+//! The address of the implementation is kept in the ledger storage and changed with
+//! [crate::Ledger::upgrade]. The new implementation migrates the storage when it is called for
+//! the first time. See [crate::Ledger_::migrate].
+use alloc::vec;
+use alloc::vec::Vec;
+use serde::Deserialize;
+
+use crate::interface::encode_result;
+use crate::pwasm::{self, Env};
+use crate::{implementation, LedgerError, Ledger_};
+
+/// Maximum size of a ledger response that the proxy can forward.
+pub const MAX_RESPONSE_SIZE: usize = 64 * 1024;
+
+/// Gas that is not passed on to the implementation so that the proxy can return the response.
+const PROXY_GAS: u64 = 50_000;
+
+/// Forwards the call input to the ledger implementation and returns its response. If the
+/// response does not fit into [MAX_RESPONSE_SIZE] bytes [LedgerError::ResponseTooLarge] is
+/// returned instead.
+///
+/// Panics if the implementation fails so that the transaction is reverted.
+pub fn call() {
+    let mut ledger = Ledger_::new(pwasm::Pwasm);
+    let implementation_address = implementation()
+        .get(&mut ledger.storage())
+        .expect("Failed to read the implementation address")
+        .expect("Implementation address is not set");
+
+    let gas = ledger.env.gas_left().saturating_sub(PROXY_GAS);
+    let mut response = vec![0; MAX_RESPONSE_SIZE];
+    let result = pwasm_ethereum::call_code(
+        gas,
+        &pwasm::Address::from(implementation_address),
+        &pwasm_ethereum::input(),
+        &mut response,
+    );
+    if result.is_err() {
+        panic!("Ledger implementation call failed");
+    }
+    pwasm_ethereum::ret(&forwarded_response(response));
+}
+
+/// Entry point of the proxy contract constructor.
+///
+/// The constructor input is the 20 byte address of the ledger implementation followed by the
+/// input for [crate::Ledger_::deploy].
+///
+/// Panics if the initialization fails so that the contract is not created.
+pub fn deploy() {
+    let input = pwasm_ethereum::input();
+    if input.len() < 20 {
+        panic!("Constructor input must start with the implementation address");
+    }
+    let (address, genesis) = input.split_at(20);
+    let mut implementation_address = [0; 20];
+    implementation_address.copy_from_slice(address);
+
+    let mut ledger = Ledger_::new(pwasm::Pwasm);
+    if let Err(err) = ledger.deploy(genesis) {
+        panic!("Failed to deploy the ledger: {:?}", err);
+    }
+    implementation().insert(&mut ledger.storage(), &implementation_address);
+}
+
+/// Returns the implementation response read into `buffer` or [LedgerError::ResponseTooLarge] if
+/// the response was cut off because it does not fit into the buffer.
+fn forwarded_response(mut buffer: Vec<u8>) -> Vec<u8> {
+    match response_len(&buffer) {
+        Some(len) => {
+            buffer.truncate(len);
+            buffer
+        }
+        None => encode_result::<()>(Err(LedgerError::ResponseTooLarge)),
+    }
+}
+
+/// Returns the length of the CBOR encoded response at the start of `data`.
+///
+/// The proxy does not know the length of the implementation’s response in advance. It reads
+/// the response into a zero-filled buffer of [MAX_RESPONSE_SIZE] bytes instead.
+fn response_len(data: &[u8]) -> Option<usize> {
+    let mut deserializer = serde_cbor::Deserializer::from_slice(data);
+    serde::de::IgnoredAny::deserialize(&mut deserializer).ok()?;
+    Some(deserializer.byte_offset())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interface::ProjectPage;
+
+    #[test]
+    fn response_len_ignores_padding() {
+        let responses = vec![
+            encode_result::<()>(Ok(())),
+            encode_result::<()>(Err(LedgerError::Paused)),
+            encode_result(Ok(ProjectPage {
+                projects: vec![],
+                next_offset: Some(10),
+            })),
+        ];
+        for response in responses {
+            let mut padded = response.clone();
+            padded.resize(MAX_RESPONSE_SIZE, 0);
+            assert_eq!(response_len(&padded), Some(response.len()));
+        }
+        assert_eq!(response_len(&[]), None);
+    }
+
+    #[test]
+    fn forwarded_response_too_large() {
+        let response = encode_result(Ok(vec![[1u8; 20]; 4000]));
+        assert!(response.len() > MAX_RESPONSE_SIZE);
+        let buffer = response[..MAX_RESPONSE_SIZE].to_vec();
+        assert_eq!(
+            forwarded_response(buffer),
+            encode_result::<()>(Err(LedgerError::ResponseTooLarge))
+        );
+
+        let response = encode_result::<()>(Ok(()));
+        let mut buffer = response.clone();
+        buffer.resize(MAX_RESPONSE_SIZE, 0);
+        assert_eq!(forwarded_response(buffer), response);
+    }
+}
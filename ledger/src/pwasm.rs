@@ -4,7 +4,8 @@
 //! as a Parity Wasm Smart Contract thhe functionality is provided by `Pwasm` using the
 //! `pwasm_ethereum` crate.
 //!
-//! For testing purposes a [pwasm::TestEnv] implementation is provided.
+//! For testing purposes a [pwasm::TestEnv] implementation is provided. [pwasm::MeteredEnv] wraps
+//! another environment and estimates the gas cost of storage access and logs.
 //!
 //! Oscoin Ledger code must not use `pwasm_ethereum` directly. It must only use functionality
 //! exposed by [pwasm::Env].
@@ -16,8 +17,25 @@ pub use pwasm_abi::types::*;
 pub trait Env {
     fn write(&mut self, key: &H256, value: &[u8; 32]);
     fn read(&self, key: &H256) -> [u8; 32];
+    /// Address of the account or contract that called the ledger.
     fn sender(&self) -> Address;
+    /// Address of the account that signed the transaction. Unlike [Env::sender] this is never a
+    /// contract.
+    fn origin(&self) -> Address;
+    /// Address of the ledger contract itself.
+    fn address(&self) -> Address;
     fn block_number(&self) -> u64;
+    /// Timestamp of the current block in seconds since the Unix epoch.
+    fn timestamp(&self) -> u64;
+    /// Amount of wei sent with the call.
+    fn value(&self) -> U256;
+    /// Gas that is left for the current call.
+    fn gas_left(&self) -> u64;
+    /// Balance of `address` in wei.
+    fn balance(&self, address: &Address) -> U256;
+    /// Emits a log entry with the given topics and data. Log entries are included in the
+    /// transaction receipt.
+    fn log(&mut self, topics: &[H256], data: &[u8]);
 }
 
 /// Implements [Env] for the Parity Wasm Smart Contract environment using the `pwasm_ethereum` crate.
@@ -36,9 +54,38 @@ impl Env for Pwasm {
         pwasm_ethereum::sender()
     }
 
+    fn origin(&self) -> Address {
+        pwasm_ethereum::origin()
+    }
+
+    fn address(&self) -> Address {
+        pwasm_ethereum::address()
+    }
+
     fn block_number(&self) -> u64 {
         pwasm_ethereum::block_number()
     }
+
+    fn timestamp(&self) -> u64 {
+        pwasm_ethereum::timestamp()
+    }
+
+    fn value(&self) -> U256 {
+        pwasm_ethereum::value()
+    }
+
+    /// Requires the KIP-6 `gasleft` extension to be enabled on the chain.
+    fn gas_left(&self) -> u64 {
+        pwasm_ethereum::gas_left()
+    }
+
+    fn balance(&self, address: &Address) -> U256 {
+        pwasm_ethereum::balance(address)
+    }
+
+    fn log(&mut self, topics: &[H256], data: &[u8]) {
+        pwasm_ethereum::log(topics, data)
+    }
 }
 
 #[cfg(any(feature = "std", test))]
@@ -48,9 +95,11 @@ pub use test_env::*;
 mod test_env {
     use super::*;
 
+    use std::cell::Cell;
     use std::collections::HashMap;
 
-    /// Implements [Env] using a [HashMap].
+    /// Implements [Env] using a [HashMap]. Emitted log entries are recorded in
+    /// [TestEnv::logs].
     ///
     /// Create an empty [TestEnv] with
     /// ```
@@ -61,13 +110,51 @@ mod test_env {
     pub struct TestEnv {
         state: HashMap<H256, [u8; 32]>,
         pub sender: Address,
+        pub origin: Address,
+        pub address: Address,
         pub block_number: u64,
+        pub timestamp: u64,
+        pub value: U256,
+        pub gas_left: u64,
+        /// Balances returned by [Env::balance]. Accounts not in the map have a zero balance.
+        pub balances: HashMap<Address, U256>,
+        pub logs: Vec<Log>,
+    }
+
+    /// A log entry recorded by [TestEnv].
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub struct Log {
+        pub topics: Vec<H256>,
+        pub data: Vec<u8>,
+    }
+
+    /// State of a [TestEnv] at some point in time. See [TestEnv::snapshot].
+    #[derive(Debug, Eq, PartialEq, Clone)]
+    pub struct Snapshot {
+        state: HashMap<H256, [u8; 32]>,
+        log_count: usize,
     }
 
     impl TestEnv {
         pub fn new() -> TestEnv {
             Default::default()
         }
+
+        /// Captures the storage and the number of recorded logs so that they can be restored with
+        /// [TestEnv::revert].
+        pub fn snapshot(&self) -> Snapshot {
+            Snapshot {
+                state: self.state.clone(),
+                log_count: self.logs.len(),
+            }
+        }
+
+        /// Restores the storage from `snapshot` and drops all logs recorded after the snapshot
+        /// was taken. This mirrors how a node discards the changes of a failed transaction.
+        pub fn revert(&mut self, snapshot: Snapshot) {
+            self.state = snapshot.state;
+            self.logs.truncate(snapshot.log_count);
+        }
     }
 
     impl Env for TestEnv {
@@ -86,8 +173,186 @@ mod test_env {
             self.sender
         }
 
+        fn origin(&self) -> Address {
+            self.origin
+        }
+
+        fn address(&self) -> Address {
+            self.address
+        }
+
         fn block_number(&self) -> u64 {
             self.block_number
         }
+
+        fn timestamp(&self) -> u64 {
+            self.timestamp
+        }
+
+        fn value(&self) -> U256 {
+            self.value
+        }
+
+        fn gas_left(&self) -> u64 {
+            self.gas_left
+        }
+
+        fn balance(&self, address: &Address) -> U256 {
+            self.balances.get(address).copied().unwrap_or_default()
+        }
+
+        fn log(&mut self, topics: &[H256], data: &[u8]) {
+            self.logs.push(Log {
+                topics: topics.to_vec(),
+                data: data.to_vec(),
+            })
+        }
+    }
+
+    /// Gas cost of reading a storage slot (`SLOAD`) as of EIP-150.
+    pub const SLOAD_GAS: u64 = 200;
+    /// Gas cost of writing a non-zero value to a zero storage slot (`SSTORE`).
+    pub const SSTORE_SET_GAS: u64 = 20_000;
+    /// Gas cost of any other storage write (`SSTORE`).
+    pub const SSTORE_RESET_GAS: u64 = 5_000;
+    /// Gas refunded for zeroing a non-zero storage slot.
+    pub const SSTORE_CLEAR_REFUND: u64 = 15_000;
+    /// Base gas cost of emitting a log entry.
+    pub const LOG_GAS: u64 = 375;
+    /// Gas cost per log topic.
+    pub const LOG_TOPIC_GAS: u64 = 375;
+    /// Gas cost per byte of log data.
+    pub const LOG_DATA_GAS: u64 = 8;
+
+    /// Counts the operations performed by [MeteredEnv] and computes their gas cost.
+    ///
+    /// Only storage access and logs are metered. Computation and the base transaction cost are
+    /// not included.
+    #[derive(Debug, Default, Clone, Eq, PartialEq)]
+    pub struct GasMeter {
+        /// Storage reads. This is a [Cell] because [Env::read] does not take `&mut self`.
+        pub reads: Cell<u64>,
+        /// Writes of a non-zero value to a zero slot.
+        pub writes_new: u64,
+        /// Writes of a non-zero value to a non-zero slot and writes of zero to a zero slot.
+        pub writes_overwrite: u64,
+        /// Writes of zero to a non-zero slot.
+        pub writes_zeroing: u64,
+        pub logs: u64,
+        pub log_topics: u64,
+        pub log_bytes: u64,
+    }
+
+    impl GasMeter {
+        /// Gas used before refunds.
+        pub fn gas(&self) -> u64 {
+            self.reads.get() * SLOAD_GAS
+                + self.writes_new * SSTORE_SET_GAS
+                + (self.writes_overwrite + self.writes_zeroing) * SSTORE_RESET_GAS
+                + self.logs * LOG_GAS
+                + self.log_topics * LOG_TOPIC_GAS
+                + self.log_bytes * LOG_DATA_GAS
+        }
+
+        /// Gas refunded for zeroing storage slots.
+        pub fn refund(&self) -> u64 {
+            self.writes_zeroing * SSTORE_CLEAR_REFUND
+        }
+
+        /// Gas used after refunds. The refund is capped at half of the used gas.
+        pub fn net_gas(&self) -> u64 {
+            let gas = self.gas();
+            gas - core::cmp::min(self.refund(), gas / 2)
+        }
+    }
+
+    /// Wraps an [Env] and meters storage access and logs with [GasMeter].
+    ///
+    /// ```
+    /// # use oscoin_ledger::pwasm::*;
+    /// let mut env = MeteredEnv::new(TestEnv::new());
+    /// env.write(&H256::zero(), &[1; 32]);
+    /// env.write(&H256::zero(), &[0; 32]);
+    /// assert_eq!(env.meter.writes_new, 1);
+    /// assert_eq!(env.meter.writes_zeroing, 1);
+    /// assert_eq!(env.meter.net_gas(), 12_500);
+    /// ```
+    #[derive(Default)]
+    pub struct MeteredEnv<E> {
+        pub inner: E,
+        pub meter: GasMeter,
+    }
+
+    impl<E: Env> MeteredEnv<E> {
+        pub fn new(inner: E) -> MeteredEnv<E> {
+            MeteredEnv {
+                inner,
+                meter: GasMeter::default(),
+            }
+        }
+
+        /// Resets the [GasMeter] and returns the previous value.
+        pub fn reset_meter(&mut self) -> GasMeter {
+            let meter = self.meter.clone();
+            self.meter = GasMeter::default();
+            meter
+        }
+    }
+
+    impl<E: Env> Env for MeteredEnv<E> {
+        fn write(&mut self, key: &H256, value: &[u8; 32]) {
+            let is_zero = *value == [0; 32];
+            let was_zero = self.inner.read(key) == [0; 32];
+            match (was_zero, is_zero) {
+                (true, false) => self.meter.writes_new += 1,
+                (false, true) => self.meter.writes_zeroing += 1,
+                _ => self.meter.writes_overwrite += 1,
+            }
+            self.inner.write(key, value)
+        }
+
+        fn read(&self, key: &H256) -> [u8; 32] {
+            self.meter.reads.set(self.meter.reads.get() + 1);
+            self.inner.read(key)
+        }
+
+        fn sender(&self) -> Address {
+            self.inner.sender()
+        }
+
+        fn origin(&self) -> Address {
+            self.inner.origin()
+        }
+
+        fn address(&self) -> Address {
+            self.inner.address()
+        }
+
+        fn block_number(&self) -> u64 {
+            self.inner.block_number()
+        }
+
+        fn timestamp(&self) -> u64 {
+            self.inner.timestamp()
+        }
+
+        fn value(&self) -> U256 {
+            self.inner.value()
+        }
+
+        fn gas_left(&self) -> u64 {
+            self.inner.gas_left()
+        }
+
+        fn balance(&self, address: &Address) -> U256 {
+            self.inner.balance(address)
+        }
+
+        fn log(&mut self, topics: &[H256], data: &[u8]) {
+            self.meter.logs += 1;
+            self.meter.log_topics += topics.len() as u64;
+            self.meter.log_bytes += data.len() as u64;
+            self.inner.log(topics, data)
+        }
     }
 }
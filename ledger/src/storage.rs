@@ -1,11 +1,141 @@
 use crate::pwasm;
 use crate::pwasm::{Vec, H256, U256};
+use core::cmp;
+use core::marker::PhantomData;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 /// Number of bytes that can be stored with the pwasm environment
 const CHUNK_SIZE: usize = 32;
 
+/// Layout of a serialized value in the 32 byte storage slots. See [Storage].
+///
+/// A value must always be read and written with the same encoding.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Encoding {
+    /// The length is stored in its own slot and the data in the subsequent slots.
+    Standard,
+    /// The length is stored in a four byte header that shares the first slot with the data.
+    /// Values of up to 28 bytes only use a single slot.
+    Compact,
+    /// Like [Encoding::Compact] but values of at least [COMPRESSION_THRESHOLD] bytes are
+    /// compressed if this makes them smaller. Compression replaces runs of zero bytes with the
+    /// length of the run.
+    CompactCompressed,
+}
+
+/// Values smaller than this are never compressed. See [Encoding::CompactCompressed].
+pub const COMPRESSION_THRESHOLD: usize = 64;
+
+/// Flag in the compact header that marks compressed data.
+const COMPRESSED_FLAG: u32 = 1 << 31;
+
+impl Encoding {
+    /// Number of bytes in the first slot that hold the header.
+    fn header_len(self) -> usize {
+        match self {
+            Encoding::Standard => 32,
+            Encoding::Compact | Encoding::CompactCompressed => 4,
+        }
+    }
+
+    /// Returns the header followed by the (possibly compressed) data.
+    fn encode(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Standard => {
+                let mut encoded = Vec::from(&H256::from(U256::from(data.len()))[..]);
+                encoded.extend_from_slice(data);
+                encoded
+            }
+            Encoding::Compact => compact_encode(data, false),
+            Encoding::CompactCompressed => {
+                if data.len() >= COMPRESSION_THRESHOLD {
+                    let compressed = compress(data);
+                    if compressed.len() < data.len() {
+                        return compact_encode(&compressed, true);
+                    }
+                }
+                compact_encode(data, false)
+            }
+        }
+    }
+
+    /// Returns the length of the stored data and whether it is compressed.
+    fn decode_header(self, first_slot: &[u8; 32]) -> (usize, bool) {
+        match self {
+            Encoding::Standard => (U256::from(*first_slot).as_usize(), false),
+            Encoding::Compact | Encoding::CompactCompressed => {
+                let header = u32::from_be_bytes([
+                    first_slot[0],
+                    first_slot[1],
+                    first_slot[2],
+                    first_slot[3],
+                ]);
+                (
+                    (header & !COMPRESSED_FLAG) as usize,
+                    header & COMPRESSED_FLAG != 0,
+                )
+            }
+        }
+    }
+}
+
+fn compact_encode(data: &[u8], compressed: bool) -> Vec<u8> {
+    assert!(
+        data.len() < COMPRESSED_FLAG as usize,
+        "Value too large for compact encoding"
+    );
+    let mut header = data.len() as u32;
+    if compressed {
+        header |= COMPRESSED_FLAG;
+    }
+    let mut encoded = Vec::from(&header.to_be_bytes()[..]);
+    encoded.extend_from_slice(data);
+    encoded
+}
+
+/// Replaces every run of up to 255 zero bytes with a zero byte followed by the length of the run.
+fn compress(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        if data[i] == 0 {
+            let run = data[i..]
+                .iter()
+                .take(255)
+                .take_while(|byte| **byte == 0)
+                .count();
+            compressed.push(0);
+            compressed.push(run as u8);
+            i += run;
+        } else {
+            compressed.push(data[i]);
+            i += 1;
+        }
+    }
+    compressed
+}
+
+/// Reverses [compress].
+fn decompress(data: &[u8]) -> Vec<u8> {
+    let mut decompressed = Vec::new();
+    let mut bytes = data.iter();
+    while let Some(byte) = bytes.next() {
+        if *byte == 0 {
+            let run = bytes.next().copied().unwrap_or(0);
+            decompressed.resize(decompressed.len() + run as usize, 0);
+        } else {
+            decompressed.push(*byte);
+        }
+    }
+    decompressed
+}
+
 /// Simple key-value store for serializable data that is backed by [pwasm::Env].
 ///
+/// Ledger code should not use raw keys. Instead it should use [StorageValue], [StorageMap], and
+/// [StorageVec] which separate namespaces and fix the type of the stored values.
+///
 /// ```
 /// # use oscoin_ledger::storage::Storage;
 /// let mut test_env = oscoin_ledger::pwasm::TestEnv::new();
@@ -13,6 +143,8 @@ const CHUNK_SIZE: usize = 32;
 /// let vec = Vec::from(b"abcdef" as &[u8]);
 /// storage.write(b"key", &vec);
 /// assert_eq!(Some(vec), storage.read(b"key").unwrap());
+/// storage.remove(b"key");
+/// assert_eq!(None, storage.read::<Vec<u8>>(b"key").unwrap());
 /// ```
 ///
 /// # Implementation
@@ -22,9 +154,12 @@ const CHUNK_SIZE: usize = 32;
 /// Then we compute the 32 byte key (say `0x123`) by hashing the storage key. We then store the
 /// length of the serialized data as the value at `0x123`. (This requires us to expand `u32` to
 /// 32 bytes). Then we store the serialized data in the subsequent keys, that is `0x124`, `0x125`,
-/// etc. Reading a value reverses this process.
+/// etc. Reading a value reverses this process. When a value is overwritten with a shorter value
+/// or removed the slots that are no longer used are zeroed.
 ///
-/// This mechanism is similar to [what Solidity does][solidity-store].
+/// This mechanism is similar to [what Solidity does][solidity-store]. It is the
+/// [Encoding::Standard]. The methods with the `_encoded` suffix allow using a more compact
+/// [Encoding].
 ///
 /// [solidity-store]: https://medium.com/@hayeah/diving-into-the-ethereum-vm-the-hidden-costs-of-arrays-28e119f04a9b
 ///
@@ -41,7 +176,27 @@ impl<'a> Storage<'a> {
         &mut self,
         key: &[u8],
     ) -> serde_cbor::Result<Option<T>> {
-        let data = self.read_bytes(key);
+        self.read_encoded(key, Encoding::Standard)
+    }
+
+    pub fn write<T: serde::Serialize>(&mut self, key: &[u8], value: &T) {
+        self.write_encoded(key, value, Encoding::Standard)
+    }
+
+    /// Removes the value stored under `key` by zeroing all slots that hold it. Subsequent reads
+    /// of `key` return `None`. Does nothing if no value is stored under `key`.
+    ///
+    /// Zeroing slots reclaims them, which gives a gas refund.
+    pub fn remove(&mut self, key: &[u8]) {
+        self.remove_encoded(key, Encoding::Standard)
+    }
+
+    pub fn read_encoded<T: serde::de::DeserializeOwned>(
+        &mut self,
+        key: &[u8],
+        encoding: Encoding,
+    ) -> serde_cbor::Result<Option<T>> {
+        let data = self.read_bytes(key, encoding);
         if data.is_empty() {
             Ok(None)
         } else {
@@ -49,36 +204,386 @@ impl<'a> Storage<'a> {
         }
     }
 
-    pub fn write<T: serde::Serialize>(&mut self, key: &[u8], value: &T) {
+    pub fn write_encoded<T: serde::Serialize>(
+        &mut self,
+        key: &[u8],
+        value: &T,
+        encoding: Encoding,
+    ) {
         let data = serde_cbor::to_vec(value).expect("Serialization can never fail");
-        self.write_bytes(key, &data)
+        self.write_bytes(key, &data, encoding)
+    }
+
+    /// See [Storage::remove].
+    pub fn remove_encoded(&mut self, key: &[u8], encoding: Encoding) {
+        let key_hash = U256::from(pwasm_std::keccak(key));
+        let old_slot_count = self.slot_count(key_hash, encoding);
+        self.clear_slots(key_hash, 0, old_slot_count);
+    }
+
+    /// Returns `true` if a value is stored under `key`. This works for all encodings.
+    pub fn contains(&mut self, key: &[u8]) -> bool {
+        let key_hash = pwasm_std::keccak(key);
+        self.env.read(&key_hash) != [0; 32]
     }
 
-    fn write_bytes(&mut self, key: &[u8], value: &[u8]) {
+    /// Writes `value` and zeroes the slots of the previous value under `key` that are not
+    /// overwritten so that no stale data is left behind.
+    fn write_bytes(&mut self, key: &[u8], value: &[u8], encoding: Encoding) {
         let key_hash = U256::from(pwasm_std::keccak(key));
-        let u256_len = U256::from(value.len());
-        self.env
-            .write(&H256::from(key_hash), &H256::from(u256_len).into());
-        let chunks = value.chunks(CHUNK_SIZE);
-        for (chunk, i) in chunks.zip(1..) {
+        let old_slot_count = self.slot_count(key_hash, encoding);
+        let encoded = encoding.encode(value);
+        let chunks = encoded.chunks(CHUNK_SIZE);
+        let new_slot_count = chunks.len();
+        for (chunk, i) in chunks.zip(0..) {
             let fixed_chunk = padded_bytes_32(chunk);
             self.env.write(&H256::from(key_hash + i), &fixed_chunk);
         }
+        self.clear_slots(key_hash, new_slot_count, old_slot_count);
     }
 
-    fn read_bytes(&mut self, key: &[u8]) -> Vec<u8> {
-        let key_hash = pwasm_std::keccak(key);
-        let mut data = Vec::new();
-        let mut data_to_read = U256::from(self.env.read(&key_hash)).as_usize();
-        let mut chunk_offset = U256::from(key_hash) + 1;
-        while data_to_read > 0 {
-            let chunk = self.env.read(&H256::from(chunk_offset));
-            let len = core::cmp::min(data_to_read, CHUNK_SIZE);
-            data.extend_from_slice(&chunk.as_ref()[0..len]);
-            data_to_read = data_to_read.saturating_sub(CHUNK_SIZE);
-            chunk_offset = chunk_offset + 1;
+    fn read_bytes(&mut self, key: &[u8], encoding: Encoding) -> Vec<u8> {
+        let key_hash = U256::from(pwasm_std::keccak(key));
+        let first_slot = self.env.read(&H256::from(key_hash));
+        let (len, compressed) = encoding.decode_header(&first_slot);
+        if len == 0 {
+            return Vec::new();
+        }
+        let header_len = encoding.header_len();
+        let end = header_len + len;
+        let mut data = Vec::from(&first_slot[header_len..cmp::min(end, CHUNK_SIZE)]);
+        let mut slot = key_hash + 1;
+        while data.len() < len {
+            let chunk = self.env.read(&H256::from(slot));
+            let chunk_len = cmp::min(len - data.len(), CHUNK_SIZE);
+            data.extend_from_slice(&chunk[0..chunk_len]);
+            slot = slot + 1;
+        }
+        if compressed {
+            decompress(&data)
+        } else {
+            data
+        }
+    }
+
+    /// Number of slots used by the value stored at `key_hash`.
+    fn slot_count(&mut self, key_hash: U256, encoding: Encoding) -> usize {
+        let (len, _) = encoding.decode_header(&self.env.read(&H256::from(key_hash)));
+        if len == 0 {
+            0
+        } else {
+            (encoding.header_len() + len + CHUNK_SIZE - 1) / CHUNK_SIZE
+        }
+    }
+
+    /// Zeroes the slots with offsets `from..to` from `key_hash`.
+    fn clear_slots(&mut self, key_hash: U256, from: usize, to: usize) {
+        for i in from..to {
+            self.env
+                .write(&H256::from(key_hash + U256::from(i)), &[0; 32]);
         }
-        data
+    }
+}
+
+/// Key under which a value of type `T` is stored in [Storage].
+///
+/// A key consists of a namespace and a key within that namespace. Keys from different namespaces
+/// never collide because the storage key is the length of the namespace followed by the
+/// namespace and the key.
+///
+/// ```
+/// # use oscoin_ledger::storage::{Storage, StorageKey};
+/// let mut test_env = oscoin_ledger::pwasm::TestEnv::new();
+/// let mut storage = Storage::new(&mut test_env);
+/// let key = StorageKey::<u64>::new(b"counters", b"a");
+/// assert!(!key.contains(&mut storage));
+/// key.mutate(&mut storage, |value| *value += 2).unwrap();
+/// assert_eq!(key.get(&mut storage).unwrap(), Some(2));
+/// key.remove(&mut storage);
+/// assert_eq!(key.get(&mut storage).unwrap(), None);
+/// ```
+pub struct StorageKey<T> {
+    key: Vec<u8>,
+    encoding: Encoding,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> StorageKey<T> {
+    /// # Panics
+    ///
+    /// Panics if `namespace` is longer than 255 bytes.
+    pub fn new(namespace: &[u8], key: &[u8]) -> StorageKey<T> {
+        assert!(namespace.len() < 256, "Storage namespace is too long");
+        let mut storage_key = Vec::with_capacity(1 + namespace.len() + key.len());
+        storage_key.push(namespace.len() as u8);
+        storage_key.extend_from_slice(namespace);
+        storage_key.extend_from_slice(key);
+        StorageKey {
+            key: storage_key,
+            encoding: Encoding::Standard,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uses `encoding` to store the value instead of [Encoding::Standard].
+    pub fn with_encoding(mut self, encoding: Encoding) -> StorageKey<T> {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn get(&self, storage: &mut Storage) -> serde_cbor::Result<Option<T>> {
+        storage.read_encoded(&self.key, self.encoding)
+    }
+
+    pub fn insert(&self, storage: &mut Storage, value: &T) {
+        storage.write_encoded(&self.key, value, self.encoding)
+    }
+
+    pub fn remove(&self, storage: &mut Storage) {
+        storage.remove_encoded(&self.key, self.encoding)
+    }
+
+    pub fn contains(&self, storage: &mut Storage) -> bool {
+        storage.contains(&self.key)
+    }
+
+    /// Applies `f` to the stored value and writes the result back. If no value is stored `f`
+    /// is applied to the default value.
+    ///
+    /// Nothing is written if reading the value fails.
+    pub fn mutate<R>(
+        &self,
+        storage: &mut Storage,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> serde_cbor::Result<R>
+    where
+        T: Default,
+    {
+        let mut value = self.get(storage)?.unwrap_or_default();
+        let result = f(&mut value);
+        self.insert(storage, &value);
+        Ok(result)
+    }
+}
+
+/// A single value of type `T` that is stored in its own namespace in [Storage].
+pub struct StorageValue<T> {
+    key: StorageKey<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> StorageValue<T> {
+    pub fn new(namespace: &[u8]) -> StorageValue<T> {
+        StorageValue {
+            key: StorageKey::new(namespace, &[]),
+        }
+    }
+
+    /// Uses `encoding` to store the value instead of [Encoding::Standard].
+    pub fn with_encoding(self, encoding: Encoding) -> StorageValue<T> {
+        StorageValue {
+            key: self.key.with_encoding(encoding),
+        }
+    }
+
+    pub fn get(&self, storage: &mut Storage) -> serde_cbor::Result<Option<T>> {
+        self.key.get(storage)
+    }
+
+    pub fn insert(&self, storage: &mut Storage, value: &T) {
+        self.key.insert(storage, value)
+    }
+
+    pub fn remove(&self, storage: &mut Storage) {
+        self.key.remove(storage)
+    }
+
+    pub fn contains(&self, storage: &mut Storage) -> bool {
+        self.key.contains(storage)
+    }
+
+    /// See [StorageKey::mutate].
+    pub fn mutate<R>(
+        &self,
+        storage: &mut Storage,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> serde_cbor::Result<R>
+    where
+        T: Default,
+    {
+        self.key.mutate(storage, f)
+    }
+}
+
+/// Map from keys of type `K` to values of type `V` that is stored in its own namespace in
+/// [Storage].
+///
+/// A [StorageMap] only describes where the entries are stored. It does not hold any data itself.
+///
+/// ```
+/// # use oscoin_ledger::storage::{Storage, StorageMap};
+/// let mut test_env = oscoin_ledger::pwasm::TestEnv::new();
+/// let mut storage = Storage::new(&mut test_env);
+/// let balances = StorageMap::<[u8; 2], u64>::new(b"balances");
+/// balances.insert(&mut storage, &[1, 2], &10);
+/// assert_eq!(balances.get(&mut storage, &[1, 2]).unwrap(), Some(10));
+/// assert!(balances.contains(&mut storage, &[1, 2]));
+/// balances.remove(&mut storage, &[1, 2]);
+/// assert_eq!(balances.get(&mut storage, &[1, 2]).unwrap(), None);
+/// ```
+pub struct StorageMap<K, V> {
+    namespace: Vec<u8>,
+    encoding: Encoding,
+    _marker: PhantomData<(K, V)>,
+}
+
+impl<K: AsRef<[u8]>, V: Serialize + DeserializeOwned> StorageMap<K, V> {
+    pub fn new(namespace: &[u8]) -> StorageMap<K, V> {
+        StorageMap {
+            namespace: Vec::from(namespace),
+            encoding: Encoding::Standard,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uses `encoding` to store the values instead of [Encoding::Standard].
+    pub fn with_encoding(mut self, encoding: Encoding) -> StorageMap<K, V> {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Returns the [StorageKey] of the entry for `key`.
+    pub fn key(&self, key: &K) -> StorageKey<V> {
+        StorageKey::new(&self.namespace, key.as_ref()).with_encoding(self.encoding)
+    }
+
+    pub fn get(&self, storage: &mut Storage, key: &K) -> serde_cbor::Result<Option<V>> {
+        self.key(key).get(storage)
+    }
+
+    pub fn insert(&self, storage: &mut Storage, key: &K, value: &V) {
+        self.key(key).insert(storage, value)
+    }
+
+    pub fn remove(&self, storage: &mut Storage, key: &K) {
+        self.key(key).remove(storage)
+    }
+
+    pub fn contains(&self, storage: &mut Storage, key: &K) -> bool {
+        self.key(key).contains(storage)
+    }
+
+    /// See [StorageKey::mutate].
+    pub fn mutate<R>(
+        &self,
+        storage: &mut Storage,
+        key: &K,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> serde_cbor::Result<R>
+    where
+        V: Default,
+    {
+        self.key(key).mutate(storage, f)
+    }
+}
+
+/// Growable array of values of type `T` that is stored in its own namespace in [Storage].
+///
+/// Like [StorageMap] a [StorageVec] only describes where the data is stored. The length of the
+/// array and each element are stored under their own [StorageKey]. This is similar to how
+/// Solidity stores dynamic arrays. Accessing or appending a single element has constant cost
+/// regardless of the length of the array.
+///
+/// ```
+/// # use oscoin_ledger::storage::{Storage, StorageVec};
+/// let mut test_env = oscoin_ledger::pwasm::TestEnv::new();
+/// let mut storage = Storage::new(&mut test_env);
+/// let names = StorageVec::<String>::new(b"names");
+/// names.push(&mut storage, &"a".to_owned()).unwrap();
+/// names.push(&mut storage, &"b".to_owned()).unwrap();
+/// assert_eq!(names.len(&mut storage).unwrap(), 2);
+/// assert_eq!(names.get(&mut storage, 1).unwrap(), Some("b".to_owned()));
+/// assert_eq!(names.swap_remove(&mut storage, 0).unwrap(), Some("a".to_owned()));
+/// assert_eq!(names.get(&mut storage, 0).unwrap(), Some("b".to_owned()));
+/// ```
+pub struct StorageVec<T> {
+    len: StorageKey<u64>,
+    elements: StorageMap<[u8; 8], T>,
+}
+
+impl<T: Serialize + DeserializeOwned> StorageVec<T> {
+    pub fn new(namespace: &[u8]) -> StorageVec<T> {
+        StorageVec {
+            len: StorageKey::new(namespace, &[]),
+            elements: StorageMap::new(namespace),
+        }
+    }
+
+    /// Uses `encoding` to store the length and the elements instead of [Encoding::Standard].
+    pub fn with_encoding(self, encoding: Encoding) -> StorageVec<T> {
+        StorageVec {
+            len: self.len.with_encoding(encoding),
+            elements: self.elements.with_encoding(encoding),
+        }
+    }
+
+    pub fn len(&self, storage: &mut Storage) -> serde_cbor::Result<u64> {
+        Ok(self.len.get(storage)?.unwrap_or(0))
+    }
+
+    /// Returns the element at `index` or `None` if `index` is out of bounds.
+    pub fn get(&self, storage: &mut Storage, index: u64) -> serde_cbor::Result<Option<T>> {
+        if index >= self.len(storage)? {
+            return Ok(None);
+        }
+        self.elements.get(storage, &index.to_be_bytes())
+    }
+
+    /// Appends `value` to the array and returns its index.
+    pub fn push(&self, storage: &mut Storage, value: &T) -> serde_cbor::Result<u64> {
+        let len = self.len(storage)?;
+        self.elements.insert(storage, &len.to_be_bytes(), value);
+        self.len.insert(storage, &(len + 1));
+        Ok(len)
+    }
+
+    /// Replaces the element at `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&self, storage: &mut Storage, index: u64, value: &T) -> serde_cbor::Result<()> {
+        let len = self.len(storage)?;
+        assert!(index < len, "StorageVec index out of bounds");
+        self.elements.insert(storage, &index.to_be_bytes(), value);
+        Ok(())
+    }
+
+    /// Removes the element at `index` and returns it. The last element of the array takes its
+    /// place. Returns `None` and does nothing if `index` is out of bounds.
+    pub fn swap_remove(&self, storage: &mut Storage, index: u64) -> serde_cbor::Result<Option<T>> {
+        let len = self.len(storage)?;
+        if index >= len {
+            return Ok(None);
+        }
+        let last_index = len - 1;
+        let removed = self.elements.get(storage, &index.to_be_bytes())?;
+        if index != last_index {
+            if let Some(last) = self.elements.get(storage, &last_index.to_be_bytes())? {
+                self.elements.insert(storage, &index.to_be_bytes(), &last);
+            }
+        }
+        self.elements.remove(storage, &last_index.to_be_bytes());
+        self.len.insert(storage, &last_index);
+        Ok(removed)
+    }
+
+    /// Removes all elements and the length from the storage.
+    pub fn clear(&self, storage: &mut Storage) -> serde_cbor::Result<()> {
+        for index in 0..self.len(storage)? {
+            self.elements.remove(storage, &index.to_be_bytes());
+        }
+        self.len.remove(storage);
+        Ok(())
     }
 }
 
@@ -90,3 +595,190 @@ fn padded_bytes_32(data: &[u8]) -> [u8; 32] {
     vec.resize_with(32, Default::default);
     core::convert::TryInto::try_into(&vec[0..32]).expect("qed")
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::interface::{Account, Checkpoint, Contribution};
+    use crate::pwasm::*;
+
+    #[test]
+    fn write_shorter_value_clears_stale_chunks() {
+        let mut test_env = TestEnv::new();
+        Storage::new(&mut test_env).write(b"key", &vec![1u8; 100]);
+        Storage::new(&mut test_env).write(b"key", &vec![2u8; 10]);
+
+        assert_eq!(
+            Storage::new(&mut test_env).read(b"key").unwrap(),
+            Some(vec![2u8; 10])
+        );
+        // The short value only uses the first chunk.
+        for i in 2..=4 {
+            assert_eq!(read_chunk(&test_env, b"key", i), [0; 32]);
+        }
+    }
+
+    #[test]
+    fn remove_clears_all_slots() {
+        let mut test_env = TestEnv::new();
+        Storage::new(&mut test_env).write(b"key", &vec![1u8; 100]);
+        Storage::new(&mut test_env).remove(b"key");
+
+        for i in 0..=4 {
+            assert_eq!(read_chunk(&test_env, b"key", i), [0; 32]);
+        }
+        assert!(!Storage::new(&mut test_env).contains(b"key"));
+    }
+
+    #[test]
+    fn storage_vec_clear() {
+        let mut test_env = TestEnv::new();
+        let storage = &mut Storage::new(&mut test_env);
+        let names = StorageVec::<String>::new(b"names");
+        for name in &["a", "b", "c"] {
+            names.push(storage, &name.to_string()).unwrap();
+        }
+
+        names.clear(storage).unwrap();
+
+        assert_eq!(names.len(storage).unwrap(), 0);
+        let elements = StorageMap::<[u8; 8], String>::new(b"names");
+        for index in 0..3u64 {
+            assert!(!elements.contains(storage, &index.to_be_bytes()));
+        }
+    }
+
+    #[test]
+    fn encodings_round_trip() {
+        let values = [
+            Vec::new(),
+            vec![7u8; 1],
+            vec![0u8; 27],
+            vec![1u8; 28],
+            vec![0u8; 200],
+            (0..=255).collect::<Vec<u8>>(),
+            (0..300)
+                .map(|i| if i % 7 == 0 { i as u8 } else { 0 })
+                .collect(),
+        ];
+        for encoding in &[
+            Encoding::Standard,
+            Encoding::Compact,
+            Encoding::CompactCompressed,
+        ] {
+            let mut test_env = TestEnv::new();
+            let mut storage = Storage::new(&mut test_env);
+            for value in values.iter() {
+                storage.write_encoded(b"key", value, *encoding);
+                assert_eq!(
+                    storage.read_encoded(b"key", *encoding).unwrap(),
+                    Some(value.clone()),
+                    "{:?}",
+                    encoding
+                );
+            }
+            storage.remove_encoded(b"key", *encoding);
+            assert_eq!(
+                storage.read_encoded::<Vec<u8>>(b"key", *encoding).unwrap(),
+                None
+            );
+            for i in 0..20 {
+                assert_eq!(read_chunk(&test_env, b"key", i), [0; 32]);
+            }
+        }
+    }
+
+    #[test]
+    fn compress_round_trip() {
+        let data = [0, 0, 1, 0, 2, 3, 0, 0, 0];
+        assert_eq!(compress(&data), vec![0, 2, 1, 0, 1, 2, 3, 0, 3]);
+        assert_eq!(decompress(&compress(&data)), data.to_vec());
+
+        let long_run = vec![0u8; 600];
+        assert_eq!(compress(&long_run), vec![0, 255, 0, 255, 0, 90]);
+        assert_eq!(decompress(&compress(&long_run)), long_run);
+    }
+
+    /// Compares the gas cost of the encodings for typical values.
+    #[test]
+    fn compact_encodings_save_gas() {
+        // Serializes to 33 bytes of CBOR. The compact encoding saves one slot.
+        let short_value = vec![1u8; 31];
+        assert_eq!(
+            write_gas(&short_value, Encoding::Standard)
+                - write_gas(&short_value, Encoding::Compact),
+            SSTORE_SET_GAS
+        );
+
+        let small_value = Account {
+            nonce: 1,
+            balance: 100,
+        };
+        assert_eq!(
+            write_gas(&small_value, Encoding::Standard)
+                - write_gas(&small_value, Encoding::Compact),
+            SSTORE_SET_GAS
+        );
+
+        // A checkpoint of the first contribution to a project links to the zero hash.
+        let checkpoint = Checkpoint {
+            project_hash: [1; 32],
+            contributions: vec![Contribution {
+                prev: [0; 32],
+                commit: [2; 32],
+                author: [3; 20],
+                signoff: None,
+            }],
+            dependency_updates: Vec::new(),
+        };
+        let standard = write_gas(&checkpoint, Encoding::Standard);
+        let compact = write_gas(&checkpoint, Encoding::Compact);
+        let compressed = write_gas(&checkpoint, Encoding::CompactCompressed);
+        assert!(compact <= standard);
+        assert!(compressed < compact);
+    }
+
+    #[test]
+    fn storage_gas() {
+        let mut env = MeteredEnv::new(TestEnv::new());
+        let long_value = vec![1u8; 50];
+        let short_value = vec![1u8; 10];
+
+        // Two slots with compact encoding.
+        Storage::new(&mut env).write_encoded(b"key", &long_value, Encoding::Compact);
+        let write = env.reset_meter();
+        assert_eq!(write.writes_new, 2);
+        assert_eq!(
+            write.net_gas(),
+            2 * SSTORE_SET_GAS + write.reads.get() * SLOAD_GAS
+        );
+
+        Storage::new(&mut env).write_encoded(b"key", &short_value, Encoding::Compact);
+        let shrink = env.reset_meter();
+        assert_eq!(shrink.writes_overwrite, 1);
+        assert_eq!(shrink.writes_zeroing, 1);
+
+        Storage::new(&mut env).remove_encoded(b"key", Encoding::Compact);
+        let remove = env.reset_meter();
+        assert_eq!(remove.writes_zeroing, 1);
+        assert_eq!(remove.refund(), SSTORE_CLEAR_REFUND);
+        assert!(remove.net_gas() <= SSTORE_RESET_GAS);
+
+        // Removing a key that is not set does not write anything.
+        Storage::new(&mut env).remove_encoded(b"key", Encoding::Compact);
+        assert_eq!(env.reset_meter().gas(), SLOAD_GAS);
+    }
+
+    /// Net gas it takes to store `value` under a fresh key.
+    fn write_gas<T: Serialize>(value: &T, encoding: Encoding) -> u64 {
+        let mut env = MeteredEnv::new(TestEnv::new());
+        Storage::new(&mut env).write_encoded(b"key", value, encoding);
+        env.meter.net_gas()
+    }
+
+    /// Reads the raw slot with offset `index` from the hashed `key`. Index zero holds the length.
+    fn read_chunk(env: &TestEnv, key: &[u8], index: u64) -> [u8; 32] {
+        let key_hash = U256::from(pwasm_std::keccak(key));
+        env.read(&H256::from(key_hash + U256::from(index)))
+    }
+}
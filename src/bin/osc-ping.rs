@@ -1,12 +1,10 @@
 ///! Calls the oscoin ledger contract’s ping method and returns the output.
 use env_logger;
 
-use futures::Future;
-
 use clap::crate_version;
 use clap::App;
 
-use oscoin_client::Client;
+use oscoin_client::blocking::Client;
 
 fn main() {
     env_logger::init();
@@ -25,6 +23,6 @@ fn main() {
         .get_matches();
 
     let client = Client::new_from_file().unwrap();
-    let pong = client.ping().wait().unwrap();
+    let pong = client.ping().unwrap();
     println!("{}", pong);
 }
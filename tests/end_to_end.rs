@@ -6,29 +6,52 @@
 //! The tests will deploy the ledger contract to the node and submit transactions to it to test the
 //! counter.
 
-use oscoin_client::{AccountId, ProjectId};
+use oscoin_client::{
+    Account, AccountId, BlockNumber, Checkpoint, Contribution, DependencyUpdate, Error, Keystore,
+    LedgerError, LedgerEvent, ProjectFilter, ProjectId, SignerError, TransferError,
+};
 use oscoin_deploy::dev_account_address;
 use std::collections::BTreeSet;
-use web3::futures::Future;
 
 #[test]
 fn counter() {
     let ledger = oscoin_deploy::deploy().unwrap();
-    let client = oscoin_client::Client::new(ledger.address());
+    let client = oscoin_client::blocking::Client::new(ledger.address());
 
     for _ in 0..10 {
-        client.counter_inc(dev_account_address()).wait().unwrap();
+        client.counter_inc(dev_account_address()).unwrap();
     }
-    let counter = client.counter_value().wait().unwrap();
+    let counter = client.counter_value().unwrap();
     assert_eq!(counter, 10);
 }
 
 #[test]
-fn register_project() {
+fn async_client() {
     let ledger = oscoin_deploy::deploy().unwrap();
     let client = oscoin_client::Client::new(ledger.address());
+    let sender = dev_account_address();
 
-    let sender = client.new_account().wait().unwrap();
+    // Futures are `Send + 'static` and outlive the client that created them.
+    let counter_inc = client.counter_inc(sender);
+    let counter_value = client.counter_value();
+    drop(client);
+    let counter = std::thread::spawn(move || {
+        futures::executor::block_on(async {
+            counter_inc.await.unwrap();
+            counter_value.await.unwrap()
+        })
+    })
+    .join()
+    .unwrap();
+    assert_eq!(counter, 1);
+}
+
+#[test]
+fn register_project() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
 
     let name = "monokol";
     let description = "Looking glass into the future.";
@@ -40,10 +63,9 @@ fn register_project() {
             description.to_owned(),
             img_url.to_owned(),
         )
-        .wait()
         .unwrap();
 
-    let project = client.get_project(project_id).wait().unwrap().unwrap();
+    let project = client.get_project(project_id).unwrap().unwrap();
 
     assert_eq!(project.name, name);
     assert_eq!(project.description, description);
@@ -52,11 +74,383 @@ fn register_project() {
 }
 
 #[test]
-fn list_projects() {
+fn register_projects_concurrently() {
     let ledger = oscoin_deploy::deploy().unwrap();
     let client = oscoin_client::Client::new(ledger.address());
+    let blocking_client = oscoin_client::blocking::Client::from(client.clone());
+
+    // Registrations that are submitted at the same time may end up in the same block. Each
+    // must still return the ID of its own project.
+    let senders = (0..4)
+        .map(|_| blocking_client.new_account().unwrap())
+        .collect::<Vec<_>>();
+    let registrations = senders.iter().enumerate().map(|(i, sender)| {
+        client.register_project(
+            *sender,
+            format!("project-{}", i),
+            "Registered concurrently.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+    });
+    let project_ids =
+        futures::executor::block_on(futures::future::try_join_all(registrations)).unwrap();
+
+    for (i, (sender, project_id)) in senders.iter().zip(project_ids).enumerate() {
+        let project = blocking_client.get_project(project_id).unwrap().unwrap();
+        assert_eq!(project.name, format!("project-{}", i));
+        assert_eq!(project.members, vec![sender.to_fixed_bytes()]);
+    }
+}
+
+#[test]
+fn deploy_with_genesis() {
+    let member = [1; 20];
+    let genesis = oscoin_deploy::GenesisConfig {
+        projects: vec![oscoin_deploy::GenesisProject {
+            name: "monokol".to_owned(),
+            description: "Looking glass into the future.".to_owned(),
+            img_url: "https://monok.el/img/logo.svg".to_owned(),
+            members: vec![member],
+            id: None,
+        }],
+        balances: vec![(member, 100)],
+        ..Default::default()
+    };
+    let ledger = oscoin_deploy::deploy_with_genesis(&genesis).unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let account = client.get_account(member).unwrap();
+    assert_eq!(account.balance, 100);
+
+    let filter = ProjectFilter {
+        member: Some(member),
+        ..Default::default()
+    };
+    let page = client.list_projects_page(0, 10, filter).unwrap();
+    assert_eq!(page.projects.len(), 1);
+    assert_eq!(page.projects[0].name, "monokol");
+}
+
+#[test]
+fn pause() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+    let owner = dev_account_address();
+
+    assert_eq!(client.get_owners().unwrap(), vec![owner.to_fixed_bytes()]);
+
+    client.pause(owner).unwrap();
+    assert!(client.is_paused().unwrap());
+    match client.counter_inc(owner) {
+        Err(Error::Ledger(LedgerError::Paused)) => (),
+        result => panic!("Unexpected result {:?}", result),
+    }
+
+    client.unpause(owner).unwrap();
+    client.counter_inc(owner).unwrap();
+    assert_eq!(client.counter_value().unwrap(), 1);
+}
+
+#[test]
+fn upgrade() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+    let sender = dev_account_address();
+
+    client.counter_inc(sender).unwrap();
+    oscoin_deploy::upgrade(ledger.address()).unwrap();
+
+    assert_eq!(client.counter_value().unwrap(), 1);
+    client.counter_inc(sender).unwrap();
+    assert_eq!(client.counter_value().unwrap(), 2);
+}
+
+#[test]
+fn local_signer() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let mut keystore = Keystore::new();
+    let sender = keystore
+        .import_key_file("./dev-node/dev-key.json", "")
+        .unwrap();
+    assert_eq!(sender, dev_account_address());
+    let client: oscoin_client::blocking::Client =
+        oscoin_client::ClientBuilder::new(ledger.address())
+            .signer(keystore)
+            .build()
+            .unwrap()
+            .into();
+
+    client.counter_inc(sender).unwrap();
+    assert_eq!(client.counter_value().unwrap(), 1);
+
+    // Node accounts are not used for signing unless node signing is enabled.
+    let node_account = client.new_account().unwrap();
+    match client.counter_inc(node_account) {
+        Err(Error::Signer(SignerError::UnknownAccount(account))) => {
+            assert_eq!(account, node_account)
+        }
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn local_signer_concurrent_transactions() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let mut keystore = Keystore::new();
+    let sender = keystore
+        .import_key_file("./dev-node/dev-key.json", "")
+        .unwrap();
+    let client = oscoin_client::ClientBuilder::new(ledger.address())
+        .signer(keystore)
+        .build()
+        .unwrap();
+
+    // Transactions that are submitted at the same time by one sender must get different nonces.
+    let increments = (0..4).map(|_| client.counter_inc(sender));
+    futures::executor::block_on(futures::future::try_join_all(increments)).unwrap();
+    assert_eq!(
+        futures::executor::block_on(client.counter_value()).unwrap(),
+        4
+    );
+}
+
+#[test]
+fn export_and_import_state() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+    let sender = dev_account_address();
+
+    client.counter_inc(sender).unwrap();
+    let project_id = client
+        .register_project(
+            sender,
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+        .unwrap();
+
+    let snapshot = client.export_state().unwrap();
+    let genesis = snapshot.into_genesis().unwrap();
+    let imported_ledger = oscoin_deploy::deploy_with_genesis(&genesis).unwrap();
+    let imported_client = oscoin_client::blocking::Client::new(imported_ledger.address());
+
+    assert_eq!(imported_client.counter_value().unwrap(), 1);
+    assert_eq!(
+        imported_client.get_project(project_id).unwrap(),
+        client.get_project(project_id).unwrap()
+    );
+}
+
+#[test]
+fn unregister_project() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let project_id = client
+        .register_project(
+            sender,
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+        .unwrap();
+
+    client.unregister_project(sender, project_id).unwrap();
+
+    assert_eq!(client.get_project(project_id).unwrap(), None);
+    let projects = client.list_projects(ProjectFilter::default()).unwrap();
+    assert!(projects.is_empty());
+}
+
+#[test]
+fn add_and_remove_key() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
 
-    let sender = client.new_account().wait().unwrap();
+    let sender = client.new_account().unwrap();
+    let maintainer = client.new_account().unwrap();
+
+    let project_id = client
+        .register_project(
+            sender,
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+        .unwrap();
+
+    client
+        .add_key(sender, project_id, maintainer.to_fixed_bytes())
+        .unwrap();
+    let project = client.get_project(project_id).unwrap().unwrap();
+    assert_eq!(
+        project.members,
+        vec![sender.to_fixed_bytes(), maintainer.to_fixed_bytes()]
+    );
+
+    client
+        .remove_key(maintainer, project_id, sender.to_fixed_bytes())
+        .unwrap();
+    let project = client.get_project(project_id).unwrap().unwrap();
+    assert_eq!(project.members, vec![maintainer.to_fixed_bytes()]);
+}
+
+#[test]
+fn checkpoint() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let project_id = client
+        .register_project(
+            sender,
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+        .unwrap();
+
+    let contributions = vec![
+        Contribution {
+            prev: [0; 32],
+            commit: [1; 32],
+            author: sender.to_fixed_bytes(),
+            signoff: None,
+        },
+        Contribution {
+            prev: [1; 32],
+            commit: [2; 32],
+            author: sender.to_fixed_bytes(),
+            signoff: Some(sender.to_fixed_bytes()),
+        },
+    ];
+    let dependency_id = client
+        .register_project(
+            sender,
+            "sibling".to_owned(),
+            "A project to depend on.".to_owned(),
+            "https://sibling.example/logo.svg".to_owned(),
+        )
+        .unwrap();
+    let dependency_updates = vec![DependencyUpdate::Depend {
+        project_id: dependency_id,
+    }];
+    client
+        .checkpoint(
+            sender,
+            project_id,
+            [2; 32],
+            contributions.clone(),
+            dependency_updates.clone(),
+        )
+        .unwrap();
+
+    let checkpoint = client.get_checkpoint(project_id, 0).unwrap().unwrap();
+    assert_eq!(
+        checkpoint,
+        Checkpoint {
+            project_hash: [2; 32],
+            contributions,
+            dependency_updates,
+        }
+    );
+    assert_eq!(client.get_checkpoint(project_id, 1).unwrap(), None);
+
+    let dependencies = client.get_dependencies(project_id).unwrap();
+    assert_eq!(dependencies, vec![dependency_id]);
+    let dependents = client.get_dependents(dependency_id).unwrap();
+    assert_eq!(dependents, vec![project_id]);
+}
+
+#[test]
+fn transfer() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let receiver = client.new_account().unwrap();
+
+    // A freshly deployed ledger has no Oscoin so we can only transfer nothing.
+    client
+        .transfer(sender, receiver.to_fixed_bytes(), 0)
+        .unwrap();
+
+    let sender_account = client.get_account(sender.to_fixed_bytes()).unwrap();
+    assert_eq!(
+        sender_account,
+        Account {
+            nonce: 1,
+            balance: 0
+        }
+    );
+    let receiver_account = client.get_account(receiver.to_fixed_bytes()).unwrap();
+    assert_eq!(receiver_account, Account::default());
+}
+
+#[test]
+fn rejected_update_returns_ledger_error() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let receiver = client.new_account().unwrap();
+
+    let result = client.transfer(sender, receiver.to_fixed_bytes(), 1);
+    match result {
+        Err(Error::Ledger(LedgerError::Transfer(TransferError::InsufficientBalance))) => (),
+        _ => panic!("Unexpected result {:?}", result),
+    }
+
+    // The failed transfer did not change the sender’s account.
+    let sender_account = client.get_account(sender.to_fixed_bytes()).unwrap();
+    assert_eq!(sender_account, Account::default());
+}
+
+#[test]
+fn events() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let member = client.new_account().unwrap();
+    let project_id = client
+        .register_project(
+            sender,
+            "monokol".to_owned(),
+            "Looking glass into the future.".to_owned(),
+            "https://monok.el/img/logo.svg".to_owned(),
+        )
+        .unwrap();
+    client
+        .add_key(sender, project_id, member.to_fixed_bytes())
+        .unwrap();
+
+    let events = client
+        .events(BlockNumber::Earliest, BlockNumber::Latest)
+        .unwrap();
+    assert_eq!(
+        events,
+        vec![
+            LedgerEvent::ProjectRegistered {
+                project_id,
+                creator: sender.to_fixed_bytes(),
+            },
+            LedgerEvent::MemberAdded {
+                project_id,
+                account: member.to_fixed_bytes(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn list_projects() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
 
     let img_url_vec: Vec<String> = (0..5)
         .map(|ix| "https://img.examples.com/".to_owned() + &ix.to_string())
@@ -73,13 +467,12 @@ fn list_projects() {
                 "description".to_owned(),
                 url.to_owned(),
             )
-            .wait()
             .unwrap();
 
         id_set.insert(id);
     }
 
-    let project_list = client.list_projects().wait().unwrap();
+    let project_list = client.list_projects(ProjectFilter::default()).unwrap();
 
     // Check that ids are returned correctly per proejct.
     assert_eq!(id_set, project_list.clone().iter().map(|p| p.id).collect());
@@ -110,3 +503,56 @@ fn list_projects() {
         member_vec_set
     )
 }
+
+#[test]
+fn list_projects_page() {
+    let ledger = oscoin_deploy::deploy().unwrap();
+    let client = oscoin_client::blocking::Client::new(ledger.address());
+
+    let sender = client.new_account().unwrap();
+    let member = client.new_account().unwrap();
+
+    let mut project_ids = Vec::new();
+    for name in &["monokol", "monorepo", "other"] {
+        let project_id = client
+            .register_project(
+                sender,
+                name.to_string(),
+                "description".to_owned(),
+                "https://img.examples.com/".to_owned(),
+            )
+            .unwrap();
+        project_ids.push(project_id);
+    }
+    client
+        .add_key(sender, project_ids[2], member.to_fixed_bytes())
+        .unwrap();
+
+    let page = client
+        .list_projects_page(0, 2, ProjectFilter::default())
+        .unwrap();
+    assert_eq!(page.projects.len(), 2);
+    assert_eq!(page.next_offset, Some(2));
+
+    let by_name = client
+        .list_projects(ProjectFilter {
+            member: None,
+            name_prefix: Some("mono".to_owned()),
+        })
+        .unwrap();
+    assert_eq!(
+        by_name.iter().map(|p| p.id).collect::<BTreeSet<_>>(),
+        project_ids[0..2].iter().cloned().collect()
+    );
+
+    let by_member = client
+        .list_projects(ProjectFilter {
+            member: Some(member.to_fixed_bytes()),
+            name_prefix: None,
+        })
+        .unwrap();
+    assert_eq!(
+        by_member.iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![project_ids[2]]
+    );
+}